@@ -1,77 +1,100 @@
+use crate::token::{ Span };
+
 #[derive(Clone)]
 pub enum Ast {
 
     Program {
-        statements: Box<Vec<Box<Ast>>>
+        statements: Box<Vec<Box<Ast>>>,
+        span: Span,
     },
 
     ExpressionStatement {
         expression: Box<Ast>,
+        span: Span,
     },
 
     LetStatement {
         identifier: Box<Ast>,
         value: Box<Ast>,
+        span: Span,
     },
 
     ReturnStatement {
         return_value: Box<Ast>,
+        span: Span,
     },
 
     Identifier {
         value: Box<String>,
+        span: Span,
     },
 
     IfExpression {
         condition: Box<Ast>,              // BlockStatement
         body: Box<Ast>,                   // BlockStatement
         alternative: Option<Box<Ast>>,  // None | BlockStatement
+        span: Span,
     },
 
     BlockStatement {
         statements: Box<Vec<Box<Ast>>>,
+        span: Span,
     },
 
     FunctionLiteral {
         arguments: Box<Vec<Box<Ast>>>,  // Identifier List
         body: Box<Ast>,                 // Block Statement
+        span: Span,
     },
 
     ArrayLiteral {
         elements: Box<Vec<Box<Ast>>>,   // Expression List
+        span: Span,
+    },
+
+    HashLiteral {
+        pairs: Box<Vec<(Box<Ast>, Box<Ast>)>>,  // (Expression, Expression) List
+        span: Span,
     },
 
     PrefixExpression {
         operator: Box<String>,    // '!' | '-'
         right: Box<Ast>,          // Expression
+        span: Span,
     },
 
     InfixExpression {
         left: Box<Ast>,           // Expression
         operator: Box<String>,    // '+' | '-' | '*' | '/' | '==' | '!='
         right: Box<Ast>,          // Expression
+        span: Span,
     },
 
     CallExpression {
         function: Box<Ast>,             // Identifier
         arguments: Box<Vec<Box<Ast>>>,  // Expression List
+        span: Span,
     },
 
     IndexExpression {
         left: Box<Ast>,
         index: Box<Ast>,
+        span: Span,
     },
 
     Integer {
         value: i64,
+        span: Span,
     },
 
     Boolean {
         value: bool,
+        span: Span,
     },
 
     StringLiteral {
         value: Box<String>,
+        span: Span,
     },
 
 }
@@ -81,33 +104,33 @@ impl Ast {
     pub fn inspect(&self) -> String {
         let mut string = "".to_string();
         match self {
-            Ast::Program { statements } => {
+            Ast::Program { statements, .. } => {
                 for statement in (*statements).iter() {
-                    string = format!("{}{}", string, (*statement).inspect().to_string());
+                    string = format!("{}{}", string, (*statement).inspect());
                 }
             },
-            Ast::ExpressionStatement { expression } => string = format!("{}", (*expression).inspect()), 
-            Ast::LetStatement { identifier, value } => string = format!("let {} = {};", (*identifier).inspect(), (*value).inspect()), 
-            Ast::ReturnStatement { return_value } => string = format!("return {};", (*return_value).inspect()),
-            Ast::Identifier { value } => string = format!("{}", value),
-            Ast::IfExpression { condition, body, alternative } => {
+            Ast::ExpressionStatement { expression, .. } => string = (*expression).inspect().to_string(), 
+            Ast::LetStatement { identifier, value, .. } => string = format!("let {} = {};", (*identifier).inspect(), (*value).inspect()), 
+            Ast::ReturnStatement { return_value, .. } => string = format!("return {};", (*return_value).inspect()),
+            Ast::Identifier { value, .. } => string = format!("{}", value),
+            Ast::IfExpression { condition, body, alternative, .. } => {
                 string = format!("if({}){{ {} }}", (*condition).inspect(), (*body).inspect());
                 if let Some(value) = alternative {
                     string = format!("{} else{{ {} }}", string, (*value).inspect());
                 }
             },
-            Ast::BlockStatement { statements } => {
+            Ast::BlockStatement { statements, .. } => {
                 for (i, statement) in (*statements).iter().enumerate() {
                     if i == 0 {
-                        string = format!("{}", (*statement).inspect());
+                        string = (*statement).inspect().to_string();
                     }
                     else {
                         string = format!("{}{}", string, (*statement).inspect());
                     }
                 }
             },
-            Ast::FunctionLiteral { arguments, body} => {
-                string = format!("fn(");
+            Ast::FunctionLiteral { arguments, body, .. } => {
+                string = "fn(".to_string();
                 for (i, argument) in arguments.iter().enumerate() {
                     if i == 0 {
                         string = format!("{}{}", string, (*argument).inspect());
@@ -118,8 +141,8 @@ impl Ast {
                 }
                 string = format!("{}){{ {} }}", string, (*body).inspect());
             },
-            Ast::ArrayLiteral { elements }        => {
-                string = format!("[");
+            Ast::ArrayLiteral { elements, .. }    => {
+                string = "[".to_string();
                 for (i, element) in elements.iter().enumerate() {
                     if i == 0 {
                         string = format!("{}{}", string, (*element).inspect());
@@ -130,9 +153,21 @@ impl Ast {
                 }
                 string = format!("{}]", string);
             },
-            Ast::PrefixExpression { operator, right} => string = format!("({}{})", *operator, (*right).inspect()),
-            Ast::InfixExpression { left, operator, right} => string = format!("({} {} {})", (*left).inspect(), *operator, (*right).inspect()),
-            Ast::CallExpression { function, arguments } => {
+            Ast::HashLiteral { pairs, .. }    => {
+                string = "{".to_string();
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i == 0 {
+                        string = format!("{}{}:{}", string, (*key).inspect(), (*value).inspect());
+                    }
+                    else {
+                        string = format!("{},{}:{}", string, (*key).inspect(), (*value).inspect());
+                    }
+                }
+                string = format!("{}}}", string);
+            },
+            Ast::PrefixExpression { operator, right, .. } => string = format!("({}{})", *operator, (*right).inspect()),
+            Ast::InfixExpression { left, operator, right, .. } => string = format!("({} {} {})", (*left).inspect(), *operator, (*right).inspect()),
+            Ast::CallExpression { function, arguments, .. } => {
                 string = format!("{}(", (*function).inspect());
                 for (i, argument) in arguments.iter().enumerate() {
                     if i == 0 {
@@ -144,15 +179,95 @@ impl Ast {
                 }
                 string = format!("{})", string);
             },
-            Ast::IndexExpression { left, index }     => string = format!("{}[{}]", (*left).inspect(), (*index).inspect()),
-            Ast::Integer { value } => string = format!("{}", value),
-            Ast::Boolean { value } => string = format!("{}", value),
-            Ast::StringLiteral { value } => string = value.to_string(),
+            Ast::IndexExpression { left, index, .. } => string = format!("{}[{}]", (*left).inspect(), (*index).inspect()),
+            Ast::Integer { value, .. } => string = format!("{}", value),
+            Ast::Boolean { value, .. } => string = format!("{}", value),
+            Ast::StringLiteral { value, .. } => string = value.to_string(),
         }
 
         string
     }
 
+    // One node per line, children indented under their parent.
+    pub fn tree(&self) -> String {
+        let mut string = String::new();
+        self.write_tree(0, &mut string);
+        string
+    }
+
+    fn write_tree(&self, depth: usize, string: &mut String) {
+        let detail = match self {
+            Ast::Identifier { value, .. }           => format!(" {}", value),
+            Ast::PrefixExpression { operator, .. }  |
+            Ast::InfixExpression { operator, .. }   => format!(" {}", operator),
+            Ast::Integer { value, .. }              => format!(" {}", value),
+            Ast::Boolean { value, .. }              => format!(" {}", value),
+            Ast::StringLiteral { value, .. }        => format!(" {:?}", value),
+            _                                       => "".to_string(),
+        };
+        string.push_str(&format!("{}{}{}\n", "  ".repeat(depth), self.kind(), detail));
+
+        let children: Vec<&Ast> = match self {
+            Ast::Program { statements, .. }                     |
+            Ast::BlockStatement { statements, .. }              => statements.iter().map(|s| s.as_ref()).collect(),
+            Ast::ExpressionStatement { expression, .. }         => vec![expression],
+            Ast::LetStatement { identifier, value, .. }         => vec![identifier, value],
+            Ast::ReturnStatement { return_value, .. }           => vec![return_value],
+            Ast::IfExpression { condition, body, alternative, .. } => {
+                let mut children: Vec<&Ast> = vec![condition, body];
+                if let Some(value) = alternative {
+                    children.push(value);
+                }
+                children
+            },
+            Ast::FunctionLiteral { arguments, body, .. }        => {
+                let mut children: Vec<&Ast> = arguments.iter().map(|a| a.as_ref()).collect();
+                children.push(body);
+                children
+            },
+            Ast::ArrayLiteral { elements, .. }                  => elements.iter().map(|e| e.as_ref()).collect(),
+            Ast::HashLiteral { pairs, .. }                      => pairs.iter().flat_map(|(k, v)| vec![k.as_ref(), v.as_ref()]).collect(),
+            Ast::PrefixExpression { right, .. }                 => vec![right],
+            Ast::InfixExpression { left, right, .. }            => vec![left, right],
+            Ast::CallExpression { function, arguments, .. }     => {
+                let mut children: Vec<&Ast> = vec![function];
+                children.extend(arguments.iter().map(|a| a.as_ref()));
+                children
+            },
+            Ast::IndexExpression { left, index, .. }            => vec![left, index],
+            Ast::Identifier {..}                                |
+            Ast::Integer {..}                                   |
+            Ast::Boolean {..}                                   |
+            Ast::StringLiteral {..}                             => vec![],
+        };
+
+        for child in children {
+            child.write_tree(depth + 1, string);
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Ast::Program { span, .. }             |
+            Ast::ExpressionStatement { span, .. } |
+            Ast::LetStatement { span, .. }        |
+            Ast::ReturnStatement { span, .. }     |
+            Ast::Identifier { span, .. }          |
+            Ast::IfExpression { span, .. }        |
+            Ast::BlockStatement { span, .. }      |
+            Ast::FunctionLiteral { span, .. }     |
+            Ast::ArrayLiteral { span, .. }        |
+            Ast::HashLiteral { span, .. }         |
+            Ast::PrefixExpression { span, .. }    |
+            Ast::InfixExpression { span, .. }     |
+            Ast::CallExpression { span, .. }      |
+            Ast::IndexExpression { span, .. }     |
+            Ast::Integer { span, .. }             |
+            Ast::Boolean { span, .. }             |
+            Ast::StringLiteral { span, .. }   => *span,
+        }
+    }
+
     pub fn kind(&self) -> String {
         match self {
             Ast::Program {..}             => "Program".to_string(),
@@ -164,6 +279,7 @@ impl Ast {
             Ast::BlockStatement {..}      => "BlockStatement".to_string(),
             Ast::FunctionLiteral {..}     => "FunctionLiteral".to_string(),
             Ast::ArrayLiteral {..}        => "ArrayLiteral".to_string(),
+            Ast::HashLiteral {..}         => "HashLiteral".to_string(),
             Ast::PrefixExpression {..}    => "PrefixExpression".to_string(),
             Ast::InfixExpression {..}     => "InfixExpression".to_string(),
             Ast::CallExpression {..}      => "CallExpression".to_string(),
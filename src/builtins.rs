@@ -0,0 +1,106 @@
+use crate::object::{ BuiltinFunction, Object };
+
+// Compiled code refers to builtins by their position in this table
+// (`OpGetBuiltin <index>`), so new entries must only ever be appended.
+pub const BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("len",     len),
+    ("puts",    puts),
+    ("first",   first),
+    ("last",    last),
+    ("rest",    rest),
+    ("push",    push),
+];
+
+pub fn lookup(name: &str) -> Option<Object> {
+    BUILTINS.iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, function)| Object::Builtin { function: *function })
+}
+
+fn len(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(1, arguments.len());
+    }
+
+    match &arguments[0] {
+        Object::String { value }    => Object::Integer { value: value.chars().count() as i64 },
+        Object::Array { elements }  => Object::Integer { value: elements.len() as i64 },
+        Object::Hash { pairs }      => Object::Integer { value: pairs.len() as i64 },
+        _                           => new_error(format!("argument to `len` not supported, got {}", arguments[0].kind())),
+    }
+}
+
+fn puts(arguments: Vec<Object>) -> Object {
+    for argument in arguments {
+        println!("{}", argument.inspect());
+    }
+
+    Object::Null
+}
+
+fn first(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(1, arguments.len());
+    }
+
+    match &arguments[0] {
+        Object::Array { elements }  => match elements.first() {
+            Some(value) => value.clone(),
+            None        => Object::Null,
+        },
+        _                           => new_error(format!("argument to `first` must be Array, got {}", arguments[0].kind())),
+    }
+}
+
+fn last(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(1, arguments.len());
+    }
+
+    match &arguments[0] {
+        Object::Array { elements }  => match elements.last() {
+            Some(value) => value.clone(),
+            None        => Object::Null,
+        },
+        _                           => new_error(format!("argument to `last` must be Array, got {}", arguments[0].kind())),
+    }
+}
+
+fn rest(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(1, arguments.len());
+    }
+
+    match &arguments[0] {
+        Object::Array { elements }  => {
+            if elements.is_empty() {
+                return Object::Null;
+            }
+            Object::Array { elements: elements[1..].to_vec() }
+        },
+        _                           => new_error(format!("argument to `rest` must be Array, got {}", arguments[0].kind())),
+    }
+}
+
+fn push(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 {
+        return wrong_number_of_arguments(2, arguments.len());
+    }
+
+    match &arguments[0] {
+        Object::Array { elements }  => {
+            let mut elements = elements.clone();
+            elements.push(arguments[1].clone());
+            Object::Array { elements }
+        },
+        _                           => new_error(format!("argument to `push` must be Array, got {}", arguments[0].kind())),
+    }
+}
+
+fn wrong_number_of_arguments(want: usize, got: usize) -> Object {
+    new_error(format!("wrong number of arguments: want={}, got={}", want, got))
+}
+
+fn new_error(message: String) -> Object {
+    Object::Error { message, span: None }
+}
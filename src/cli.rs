@@ -0,0 +1,351 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{ self, IsTerminal, Read };
+use std::path::{ Path };
+use std::rc::Rc;
+
+use crate::ast::{ Ast };
+use crate::compiler::{ Bytecode, Compiler };
+use crate::diagnostic::{ render };
+use crate::environment::{ Environment };
+use crate::eval::{ eval };
+use crate::lexer::{ Lexer };
+use crate::object::{ Object };
+use crate::parser::{ Parser };
+use crate::repl;
+use crate::serialize::{ MAGIC };
+use crate::vm::{ VM };
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_FAILURE: i32 = 1;    // parse, compile or runtime error, or unreadable input
+pub const EXIT_USAGE: i32 = 2;
+
+const USAGE: &str = "usage: simiaVM [--engine=eval|vm] [script | -e <source> | -]
+       simiaVM compile <script> [-o <output>]
+       simiaVM disasm <file>
+
+With no script, reads the program from stdin when it is piped and
+starts the REPL otherwise; type :help there for its commands. Compiled
+bytecode files (.smb) given as the script always run on the VM.
+
+commands:
+  compile             compile <script> to a bytecode file, written to
+                      <output> or to <script> with an .smb extension
+  disasm              print the constant pool and instructions of a
+                      bytecode file, or of a script compiled on the fly
+
+options:
+  -e <source>         run <source> and print its value
+  --engine=eval|vm    execute with the tree-walking evaluator (default)
+                      or the bytecode VM
+  -h, --help          print this message";
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum Engine {
+    Eval,
+    Vm,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub enum Source {
+    File { path: String },
+    Expression { source: String },
+    Stdin,
+    Repl,
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub enum Command {
+    Run { engine: Engine, source: Source },
+    Compile { input: String, output: String },
+    Disasm { path: String },
+    Help,
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("compile") => return parse_compile_args(&args[1..]),
+        Some("disasm")  => return parse_disasm_args(&args[1..]),
+        _               => (),
+    }
+
+    let mut engine = Engine::Eval;
+    let mut source = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+
+        let next_source = match arg {
+            "-h" | "--help"         => return Ok(Command::Help),
+            "-e"                    => {
+                i += 1;
+                match args.get(i) {
+                    Some(value) => Source::Expression { source: value.to_string() },
+                    None        => return Err("option -e requires an argument".to_string()),
+                }
+            },
+            "-"                     => Source::Stdin,
+            _ if arg.starts_with("--engine=") => {
+                engine = parse_engine(&arg["--engine=".len()..])?;
+                i += 1;
+                continue;
+            },
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _                       => Source::File { path: arg.to_string() },
+        };
+
+        if source.is_some() {
+            return Err(format!("unexpected argument: {}", arg));
+        }
+        source = Some(next_source);
+        i += 1;
+    }
+
+    let source = match source {
+        Some(value)                             => value,
+        None if io::stdin().is_terminal()       => Source::Repl,
+        None                                    => Source::Stdin,
+    };
+
+    Ok(Command::Run { engine, source })
+}
+
+fn parse_compile_args(args: &[String]) -> Result<Command, String> {
+    let mut input = None;
+    let mut output = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+
+        match arg {
+            "-o"                        => {
+                i += 1;
+                match args.get(i) {
+                    Some(value) => output = Some(value.to_string()),
+                    None        => return Err("option -o requires an argument".to_string()),
+                }
+            },
+            _ if arg.starts_with('-')   => return Err(format!("unknown option: {}", arg)),
+            _ if input.is_some()        => return Err(format!("unexpected argument: {}", arg)),
+            _                           => input = Some(arg.to_string()),
+        }
+        i += 1;
+    }
+
+    let input = match input {
+        Some(value) => value,
+        None        => return Err("compile requires a script".to_string()),
+    };
+
+    let output = match output {
+        Some(value) => value,
+        None        => Path::new(&input).with_extension("smb").to_string_lossy().to_string(),
+    };
+
+    Ok(Command::Compile { input, output })
+}
+
+fn parse_disasm_args(args: &[String]) -> Result<Command, String> {
+    match args {
+        [path] if !path.starts_with('-')    => Ok(Command::Disasm { path: path.to_string() }),
+        []                                  => Err("disasm requires a file".to_string()),
+        _                                   => Err(format!("unexpected argument: {}", args[args.len() - 1])),
+    }
+}
+
+fn parse_engine(name: &str) -> Result<Engine, String> {
+    match name {
+        "eval"  => Ok(Engine::Eval),
+        "vm"    => Ok(Engine::Vm),
+        _       => Err(format!("unknown engine: {} (expected eval or vm)", name)),
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let command = match parse_args(args) {
+        Ok(value)   => value,
+        Err(error)  => {
+            eprintln!("simiaVM: {}\n\n{}", error, USAGE);
+            return EXIT_USAGE;
+        },
+    };
+
+    let (engine, source) = match command {
+        Command::Help                       => {
+            println!("{}", USAGE);
+            return EXIT_SUCCESS;
+        },
+        Command::Compile { input, output }  => return compile_file(&input, &output),
+        Command::Disasm { path }            => return disassemble_file(&path),
+        Command::Run { engine, source }     => (engine, source),
+    };
+
+    let (input, origin, print_result) = match source {
+        Source::Repl                    => {
+            repl::start(engine);
+            return EXIT_SUCCESS;
+        },
+        Source::Expression { source }   => (source, "<expression>".to_string(), true),
+        Source::File { path }           => match read_file(&path) {
+            Ok(File::Bytecode(bytecode))    => return report(run_bytecode(bytecode), false),
+            Ok(File::Source(value))         => (value, path, false),
+            Err(error)                      => {
+                eprintln!("simiaVM: {}", error);
+                return EXIT_FAILURE;
+            },
+        },
+        Source::Stdin                   => {
+            let mut input = String::new();
+            if let Err(error) = io::stdin().read_to_string(&mut input) {
+                eprintln!("simiaVM: cannot read stdin: {}", error);
+                return EXIT_FAILURE;
+            }
+            (input, "<stdin>".to_string(), false)
+        },
+    };
+
+    report(execute(&input, &origin, engine), print_result)
+}
+
+fn report(result: Result<Object, Vec<String>>, print_result: bool) -> i32 {
+    match result {
+        Ok(object)  => {
+            if print_result {
+                println!("{}", object.inspect());
+            }
+            EXIT_SUCCESS
+        },
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            EXIT_FAILURE
+        },
+    }
+}
+
+enum File {
+    Source(String),
+    Bytecode(Bytecode),
+}
+
+// Files starting with the bytecode magic header are loaded as compiled
+// programs, anything else as source text.
+fn read_file(path: &str) -> Result<File, String> {
+    let bytes = fs::read(path).map_err(|error| format!("cannot read {}: {}", path, error))?;
+
+    if bytes.starts_with(MAGIC) {
+        return Bytecode::deserialize(&bytes)
+            .map(File::Bytecode)
+            .map_err(|error| format!("cannot load {}: {}", path, error));
+    }
+
+    String::from_utf8(bytes)
+        .map(File::Source)
+        .map_err(|_| format!("cannot read {}: not valid UTF-8", path))
+}
+
+fn compile_file(input: &str, output: &str) -> i32 {
+    let source = match read_file(input) {
+        Ok(File::Source(value))     => value,
+        Ok(File::Bytecode(_))       => {
+            eprintln!("simiaVM: {} is already compiled", input);
+            return EXIT_FAILURE;
+        },
+        Err(error)                  => {
+            eprintln!("simiaVM: {}", error);
+            return EXIT_FAILURE;
+        },
+    };
+
+    let bytes = match compile(&source, input).and_then(|bytecode| bytecode.serialize().map_err(|error| vec![error])) {
+        Ok(value)   => value,
+        Err(errors) => return report(Err(errors), false),
+    };
+
+    if let Err(error) = fs::write(output, bytes) {
+        eprintln!("simiaVM: cannot write {}: {}", output, error);
+        return EXIT_FAILURE;
+    }
+
+    EXIT_SUCCESS
+}
+
+fn disassemble_file(path: &str) -> i32 {
+    let bytecode = match read_file(path) {
+        Ok(File::Bytecode(value))   => value,
+        Ok(File::Source(source))    => match compile(&source, path) {
+            Ok(value)   => value,
+            Err(errors) => return report(Err(errors), false),
+        },
+        Err(error)                  => {
+            eprintln!("simiaVM: {}", error);
+            return EXIT_FAILURE;
+        },
+    };
+
+    print!("{}", bytecode.disassemble());
+
+    EXIT_SUCCESS
+}
+
+// Runs a whole program and returns the value of its last expression, or
+// every message that should be reported if it could not be run.
+pub fn execute(input: &str, origin: &str, engine: Engine) -> Result<Object, Vec<String>> {
+    let program = parse(input, origin)?;
+
+    match engine {
+        Engine::Eval    => {
+            let env = Rc::new(RefCell::new(Environment::new()));
+            match eval(program, &env) {
+                Object::Error { message, span: Some(span) } => {
+                    Err(vec![render(&format!("runtime error: {}", message), span, input, origin)])
+                },
+                Object::Error { message, span: None }       => Err(vec![format!("runtime error: {}", message)]),
+                object                                      => Ok(object),
+            }
+        },
+        Engine::Vm      => run_bytecode(compile_program(program)?),
+    }
+}
+
+pub fn compile(input: &str, origin: &str) -> Result<Bytecode, Vec<String>> {
+    compile_program(parse(input, origin)?)
+}
+
+fn compile_program(program: Ast) -> Result<Bytecode, Vec<String>> {
+    let mut compiler = Compiler::new();
+    match compiler.compile(program) {
+        Ok(())      => Ok(compiler.bytecode()),
+        Err(error)  => Err(vec![format!("compile error: {}", error)]),
+    }
+}
+
+fn run_bytecode(bytecode: Bytecode) -> Result<Object, Vec<String>> {
+    let mut vm = VM::new(bytecode);
+    match vm.run() {
+        Ok(())      => Ok(vm.result()),
+        Err(error)  => Err(vec![format!("runtime error: {}", error)]),
+    }
+}
+
+// Parse errors are rendered against the source; `origin` names it in the
+// location line, e.g. a file path or "<stdin>".
+pub fn parse(input: &str, origin: &str) -> Result<Ast, Vec<String>> {
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    if !parser.errors.is_empty() {
+        return Err(parser.errors.iter()
+                   .map(|error| render(&format!("parse error: {}", error), error.span(), input, origin))
+                   .collect());
+    }
+
+    match program {
+        Some(value) => Ok(value),
+        None        => Err(vec!["parse error: empty program".to_string()]),
+    }
+}
@@ -1,50 +1,275 @@
-type Instructions = Vec<u8>;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{ Deref, DerefMut };
 
-#[derive(Clone)]
+#[derive(Clone,PartialEq,Debug,Default)]
+pub struct Instructions(pub Vec<u8>);
+
+impl Instructions {
+    pub fn new() -> Instructions {
+        Instructions(Vec::new())
+    }
+}
+
+impl From<Vec<u8>> for Instructions {
+    fn from(bytes: Vec<u8>) -> Instructions {
+        Instructions(bytes)
+    }
+}
+
+impl Deref for Instructions {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl DerefMut for Instructions {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+}
+
+impl fmt::Display for Instructions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut i = 0;
+
+        while i < self.len() {
+            let def = match Opcode::from_byte(self[i]).and_then(lookup) {
+                Some(value) => value,
+                None        => {
+                    writeln!(f, "ERROR: unknown opcode {}", self[i])?;
+                    i += 1;
+                    continue;
+                },
+            };
+
+            let width: usize = def.operand_widths.iter().map(|w| *w as usize).sum();
+            if i + 1 + width > self.len() {
+                writeln!(f, "{:04} ERROR: truncated {}", i, def.name)?;
+                break;
+            }
+
+            let (operands, read) = read_operands(&def, &self[i+1..]);
+            writeln!(f, "{:04} {}", i, fmt_instruction(&def, &operands))?;
+
+            i += 1 + read;
+        }
+
+        Ok(())
+    }
+}
+
+fn fmt_instruction(def: &Definition, operands: &[usize]) -> String {
+    if operands.len() != def.operand_widths.len() {
+        return format!("ERROR: operand len {} does not match defined {}",
+                       operands.len(), def.operand_widths.len());
+    }
+
+    match operands.len() {
+        0 => def.name.to_string(),
+        1 => format!("{} {}", def.name, operands[0]),
+        2 => format!("{} {} {}", def.name, operands[0], operands[1]),
+        _ => format!("ERROR: unhandled operand count for {}", def.name),
+    }
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
 pub enum Opcode {
     OpConstant,
+
+    OpAdd,
+    OpSub,
+    OpMul,
+    OpDiv,
+
+    OpPop,
+
+    OpTrue,
+    OpFalse,
+    OpNull,
+
+    OpEqual,
+    OpNotEqual,
+    OpGreaterThan,
+    OpLessThan,
+
+    OpMinus,
+    OpBang,
+
+    OpJumpNotTruthy,
+    OpJump,
+
+    OpGetGlobal,
+    OpSetGlobal,
+    OpGetLocal,
+    OpSetLocal,
+
+    OpArray,
+    OpHash,
+    OpIndex,
+
+    OpCall,
+    OpReturnValue,
+    OpReturn,
+
+    OpGetBuiltin,
+
+    OpClosure,
+    OpGetFree,
+    OpCaptureLocal,
+    OpCaptureFree,
+    OpCurrentClosure,
+}
+
+// Every opcode in declaration order, so that `OPCODES[op as usize] == op`.
+const OPCODES: [Opcode; 33] = [
+    Opcode::OpConstant,
+    Opcode::OpAdd,
+    Opcode::OpSub,
+    Opcode::OpMul,
+    Opcode::OpDiv,
+    Opcode::OpPop,
+    Opcode::OpTrue,
+    Opcode::OpFalse,
+    Opcode::OpNull,
+    Opcode::OpEqual,
+    Opcode::OpNotEqual,
+    Opcode::OpGreaterThan,
+    Opcode::OpLessThan,
+    Opcode::OpMinus,
+    Opcode::OpBang,
+    Opcode::OpJumpNotTruthy,
+    Opcode::OpJump,
+    Opcode::OpGetGlobal,
+    Opcode::OpSetGlobal,
+    Opcode::OpGetLocal,
+    Opcode::OpSetLocal,
+    Opcode::OpArray,
+    Opcode::OpHash,
+    Opcode::OpIndex,
+    Opcode::OpCall,
+    Opcode::OpReturnValue,
+    Opcode::OpReturn,
+    Opcode::OpGetBuiltin,
+    Opcode::OpClosure,
+    Opcode::OpGetFree,
+    Opcode::OpCaptureLocal,
+    Opcode::OpCaptureFree,
+    Opcode::OpCurrentClosure,
+];
+
+impl Opcode {
+    pub fn from_byte(byte: u8) -> Option<Opcode> {
+        OPCODES.get(byte as usize).copied()
+    }
 }
 
 #[derive(Clone)]
 pub struct Definition {
-    opcode: Opcode,
-    name: String,
-    operand_widths: Vec<u8>,
+    pub opcode: Opcode,
+    pub name: String,
+    pub operand_widths: Vec<u8>,
 }
 
 pub fn lookup(op: Opcode) -> Option<Definition> {
-    match op {
-        Opcode::OpConstant => Some(Definition {
-            opcode: Opcode::OpConstant,
-            name: "OpConstant".to_string(),
-            operand_widths: vec![2],
-        }),
-        _ => {
-            eprintln!("opcode {} undifined", op as u8);
-            return None
-        },
-    }
+    let (name, operand_widths) = match op {
+        Opcode::OpConstant      => ("OpConstant", vec![2]),
+        Opcode::OpAdd           => ("OpAdd", vec![]),
+        Opcode::OpSub           => ("OpSub", vec![]),
+        Opcode::OpMul           => ("OpMul", vec![]),
+        Opcode::OpDiv           => ("OpDiv", vec![]),
+        Opcode::OpPop           => ("OpPop", vec![]),
+        Opcode::OpTrue          => ("OpTrue", vec![]),
+        Opcode::OpFalse         => ("OpFalse", vec![]),
+        Opcode::OpNull          => ("OpNull", vec![]),
+        Opcode::OpEqual         => ("OpEqual", vec![]),
+        Opcode::OpNotEqual      => ("OpNotEqual", vec![]),
+        Opcode::OpGreaterThan   => ("OpGreaterThan", vec![]),
+        Opcode::OpLessThan      => ("OpLessThan", vec![]),
+        Opcode::OpMinus         => ("OpMinus", vec![]),
+        Opcode::OpBang          => ("OpBang", vec![]),
+        Opcode::OpJumpNotTruthy => ("OpJumpNotTruthy", vec![2]),   // jump target
+        Opcode::OpJump          => ("OpJump", vec![2]),            // jump target
+        Opcode::OpGetGlobal     => ("OpGetGlobal", vec![2]),       // global index
+        Opcode::OpSetGlobal     => ("OpSetGlobal", vec![2]),       // global index
+        Opcode::OpGetLocal      => ("OpGetLocal", vec![1]),        // local index
+        Opcode::OpSetLocal      => ("OpSetLocal", vec![1]),        // local index
+        Opcode::OpArray         => ("OpArray", vec![2]),           // number of elements
+        Opcode::OpHash          => ("OpHash", vec![2]),            // number of keys and values
+        Opcode::OpIndex         => ("OpIndex", vec![]),
+        Opcode::OpCall          => ("OpCall", vec![1]),            // number of arguments
+        Opcode::OpReturnValue   => ("OpReturnValue", vec![]),
+        Opcode::OpReturn        => ("OpReturn", vec![]),
+        Opcode::OpGetBuiltin    => ("OpGetBuiltin", vec![1]),      // builtin index
+        Opcode::OpClosure       => ("OpClosure", vec![2, 1]),      // constant index, number of free variables
+        Opcode::OpGetFree       => ("OpGetFree", vec![1]),         // free variable index
+        Opcode::OpCaptureLocal  => ("OpCaptureLocal", vec![1]),    // local index
+        Opcode::OpCaptureFree   => ("OpCaptureFree", vec![1]),     // free variable index
+        Opcode::OpCurrentClosure => ("OpCurrentClosure", vec![]),
+    };
+
+    Some(Definition {
+        opcode: op,
+        name: name.to_string(),
+        operand_widths,
+    })
 }
 
-pub fn make(op: Opcode, operands: Vec<u16>) -> Vec<u8> {
+// Operands must fit their width; the compiler checks its limits before
+// emitting, so an operand that does not fit is a bug, not bad input.
+pub fn make(op: Opcode, operands: Vec<usize>) -> Vec<u8> {
 
-    let def = match lookup(op.clone()) {
+    let def = match lookup(op) {
         Some(value) => value,
         None        => return Vec::new(),
     };
     
     let mut instruction = Vec::new();
 
-    instruction.push(op.clone() as u8);
+    instruction.push(op as u8);
 
     for (i, o) in operands.iter().enumerate() {
         let width = def.operand_widths[i];
 
         match width {
-            2 => instruction.append(&mut o.to_be_bytes().to_vec()),
+            2 => match u16::try_from(*o) {
+                Ok(value)   => instruction.extend_from_slice(&value.to_be_bytes()),
+                Err(_)      => panic!("operand {} of {} does not fit in 2 bytes", o, def.name),
+            },
+            1 => match u8::try_from(*o) {
+                Ok(value)   => instruction.push(value),
+                Err(_)      => panic!("operand {} of {} does not fit in 1 byte", o, def.name),
+            },
             _ => return Vec::new(),
         }
     }
     
     instruction
 }
+
+pub fn read_operands(def: &Definition, ins: &[u8]) -> (Vec<usize>, usize) {
+    let mut operands = Vec::new();
+    let mut offset = 0;
+
+    for width in def.operand_widths.iter() {
+        match width {
+            2 => operands.push(read_u16(&ins[offset..]) as usize),
+            1 => operands.push(read_u8(&ins[offset..]) as usize),
+            _ => (),
+        }
+
+        offset += *width as usize;
+    }
+
+    (operands, offset)
+}
+
+pub fn read_u16(ins: &[u8]) -> u16 {
+    u16::from_be_bytes([ins[0], ins[1]])
+}
+
+pub fn read_u8(ins: &[u8]) -> u8 {
+    ins[0]
+}
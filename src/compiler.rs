@@ -0,0 +1,523 @@
+use std::collections::{ HashMap, HashSet };
+use std::fmt::Write;
+use std::mem;
+use std::rc::Rc;
+
+use crate::ast::{ Ast };
+use crate::builtins::{ BUILTINS };
+use crate::code::{ Instructions, Opcode, make };
+use crate::object::{ CompiledFunction, HashKey, Object };
+use crate::symbol_table::{ Symbol, SymbolScope, SymbolTable };
+
+const MAX_U8_OPERAND: usize = 255;
+const MAX_U16_OPERAND: usize = 65535;
+
+#[derive(Clone)]
+pub struct Bytecode {
+    pub instructions: Instructions,
+    pub constants: Vec<Object>,
+}
+
+impl Bytecode {
+    // Lists the constant pool, including the body of every compiled
+    // function, followed by the instructions of the main program.
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "constants:").unwrap();
+        for (i, constant) in self.constants.iter().enumerate() {
+            match constant {
+                Object::String { value }                => {
+                    writeln!(out, "  {:04} String {:?}", i, value).unwrap();
+                },
+                Object::CompiledFunction { function }   => {
+                    writeln!(out, "  {:04} CompiledFunction [{} params, {} locals]",
+                             i, function.num_parameters, function.num_locals).unwrap();
+                    write_indented(&mut out, &function.instructions, "        ");
+                },
+                _                                       => {
+                    writeln!(out, "  {:04} {} {}", i, constant.kind(), constant.inspect()).unwrap();
+                },
+            }
+        }
+
+        writeln!(out, "main:").unwrap();
+        write_indented(&mut out, &self.instructions, "  ");
+
+        out
+    }
+}
+
+fn write_indented(out: &mut String, instructions: &Instructions, indent: &str) {
+    for line in instructions.to_string().lines() {
+        writeln!(out, "{}{}", indent, line).unwrap();
+    }
+}
+
+#[derive(Clone,Copy)]
+struct EmittedInstruction {
+    opcode: Opcode,
+    position: usize,
+}
+
+// Each function literal is compiled into its own scope so that its
+// instructions do not end up in the enclosing function.
+#[derive(Clone,Default)]
+struct CompilationScope {
+    instructions: Instructions,
+    last_instruction: Option<EmittedInstruction>,
+    previous_instruction: Option<EmittedInstruction>,
+}
+
+pub struct Compiler {
+    constants: Vec<Object>,
+    literals: HashMap<HashKey, usize>,  // Index of each integer and string constant
+    symbol_table: SymbolTable,
+    scopes: Vec<CompilationScope>,
+    top_level_names: HashSet<String>,   // Names the program binds with a top-level `let`
+}
+
+impl Default for Compiler {
+    fn default() -> Compiler {
+        Compiler::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        let mut symbol_table = SymbolTable::new();
+        for (i, (name, _)) in BUILTINS.iter().enumerate() {
+            symbol_table.define_builtin(i, name);
+        }
+
+        Compiler {
+            constants: Vec::new(),
+            literals: HashMap::new(),
+            symbol_table,
+            scopes: vec![CompilationScope::default()],
+            top_level_names: HashSet::new(),
+        }
+    }
+
+    // Resumes from the symbols and constants of an earlier compilation so
+    // that a REPL line can refer to what previous lines defined.
+    pub fn new_with_state(symbol_table: SymbolTable, constants: Vec<Object>) -> Compiler {
+        let literals = constants.iter().enumerate()
+            .filter_map(|(i, constant)| constant.hash_key().map(|key| (key, i)))
+            .collect();
+
+        Compiler {
+            constants,
+            literals,
+            symbol_table,
+            scopes: vec![CompilationScope::default()],
+            top_level_names: HashSet::new(),
+        }
+    }
+
+    pub fn into_state(self) -> (SymbolTable, Vec<Object>) {
+        (self.symbol_table, self.constants)
+    }
+
+    pub fn compile(&mut self, node: Ast) -> Result<(), String> {
+        match node {
+            Ast::Program { statements, .. }             => {
+                self.top_level_names = statements.iter()
+                    .filter_map(|statement| match statement.as_ref() {
+                        Ast::LetStatement { identifier, .. }    => Some(identifier.inspect()),
+                        _                                       => None,
+                    })
+                    .collect();
+
+                for statement in *statements {
+                    self.compile(*statement)?;
+                }
+            },
+            Ast::ExpressionStatement { expression, .. } => {
+                self.compile(*expression)?;
+                self.emit(Opcode::OpPop, vec![]);
+            },
+            Ast::BlockStatement { statements, .. }      => {
+                for statement in *statements {
+                    self.compile(*statement)?;
+                }
+            },
+            Ast::LetStatement { identifier, value, .. } => {
+                // A function literal bound by `let` learns its own name so that
+                // it can refer to itself recursively.
+                match *value {
+                    Ast::FunctionLiteral { arguments, body, .. } => {
+                        self.compile_function(Some(identifier.inspect()), &arguments, *body)?;
+                    },
+                    _   => self.compile(*value)?,
+                }
+
+                let symbol = self.define(&identifier.inspect())?;
+                match symbol.scope {
+                    SymbolScope::Global => self.emit(Opcode::OpSetGlobal, vec![symbol.index]),
+                    _                   => self.emit(Opcode::OpSetLocal, vec![symbol.index]),
+                };
+            },
+            Ast::Identifier { value, .. }               => {
+                let symbol = match self.symbol_table.resolve(&value) {
+                    Some(symbol)    => symbol,
+                    None            => self.define_top_level_name(&value)?,
+                };
+                self.load_symbol(symbol)?;
+            },
+            Ast::ReturnStatement { return_value, .. }   => {
+                self.compile(*return_value)?;
+                self.emit(Opcode::OpReturnValue, vec![]);
+            },
+            Ast::IfExpression { condition, body, alternative, .. } => {
+                self.compile(*condition)?;
+
+                // Emit with a bogus offset that is back-patched once the body is compiled.
+                let jump_not_truthy_position = self.emit(Opcode::OpJumpNotTruthy, vec![9999]);
+
+                self.compile_branch(*body)?;
+
+                let jump_position = self.emit(Opcode::OpJump, vec![9999]);
+
+                let after_body_position = self.current_instructions().len();
+                self.change_operand(jump_not_truthy_position, after_body_position)?;
+
+                match alternative {
+                    Some(value) => {
+                        self.compile_branch(*value)?;
+                    },
+                    None        => {
+                        self.emit(Opcode::OpNull, vec![]);
+                    },
+                }
+
+                let after_alternative_position = self.current_instructions().len();
+                self.change_operand(jump_position, after_alternative_position)?;
+            },
+            Ast::FunctionLiteral { arguments, body, .. } => {
+                self.compile_function(None, &arguments, *body)?;
+            },
+            Ast::ArrayLiteral { elements, .. }          => {
+                let length = elements.len();
+                check_limit(length, MAX_U16_OPERAND, "elements in array literal")?;
+                for element in *elements {
+                    self.compile(*element)?;
+                }
+                self.emit(Opcode::OpArray, vec![length]);
+            },
+            Ast::HashLiteral { pairs, .. }              => {
+                let length = pairs.len();
+                check_limit(length, MAX_U16_OPERAND / 2, "pairs in hash literal")?;
+                for (key, value) in *pairs {
+                    self.compile(*key)?;
+                    self.compile(*value)?;
+                }
+                self.emit(Opcode::OpHash, vec![length * 2]);
+            },
+            Ast::PrefixExpression { operator, right, .. } => {
+                self.compile(*right)?;
+
+                match operator.as_str() {
+                    "!" => self.emit(Opcode::OpBang, vec![]),
+                    "-" => self.emit(Opcode::OpMinus, vec![]),
+                    _   => return Err(format!("unknown operator: {}", operator)),
+                };
+            },
+            Ast::InfixExpression { left, operator, right, .. } => {
+                self.compile(*left)?;
+                self.compile(*right)?;
+
+                match operator.as_str() {
+                    "+"     => self.emit(Opcode::OpAdd, vec![]),
+                    "-"     => self.emit(Opcode::OpSub, vec![]),
+                    "*"     => self.emit(Opcode::OpMul, vec![]),
+                    "/"     => self.emit(Opcode::OpDiv, vec![]),
+                    ">"     => self.emit(Opcode::OpGreaterThan, vec![]),
+                    "<"     => self.emit(Opcode::OpLessThan, vec![]),
+                    "=="    => self.emit(Opcode::OpEqual, vec![]),
+                    "!="    => self.emit(Opcode::OpNotEqual, vec![]),
+                    _       => return Err(format!("unknown operator: {}", operator)),
+                };
+            },
+            Ast::IndexExpression { left, index, .. }    => {
+                self.compile(*left)?;
+                self.compile(*index)?;
+                self.emit(Opcode::OpIndex, vec![]);
+            },
+            Ast::Integer { value, .. }                  => {
+                let constant = self.add_constant(Object::Integer { value })?;
+                self.emit(Opcode::OpConstant, vec![constant]);
+            },
+            Ast::Boolean { value, .. }                  => {
+                if value {
+                    self.emit(Opcode::OpTrue, vec![]);
+                }
+                else {
+                    self.emit(Opcode::OpFalse, vec![]);
+                }
+            },
+            Ast::StringLiteral { value, .. }            => {
+                let constant = self.add_constant(Object::String { value: *value })?;
+                self.emit(Opcode::OpConstant, vec![constant]);
+            },
+            Ast::CallExpression { function, arguments, .. } => {
+                self.compile(*function)?;
+
+                let length = arguments.len();
+                check_limit(length, MAX_U8_OPERAND, "arguments in call")?;
+                for argument in *arguments {
+                    self.compile(*argument)?;
+                }
+
+                self.emit(Opcode::OpCall, vec![length]);
+            },
+        }
+
+        Ok(())
+    }
+
+    // A branch of an if expression must leave exactly one value on the stack:
+    // the value of its last expression statement, or null if there is none.
+    fn compile_branch(&mut self, block: Ast) -> Result<(), String> {
+        self.compile(block)?;
+
+        if self.last_instruction_is(Opcode::OpPop) {
+            self.remove_last_pop();
+        }
+        else {
+            self.emit(Opcode::OpNull, vec![]);
+        }
+
+        Ok(())
+    }
+
+    fn compile_function(&mut self, name: Option<String>, parameters: &[Box<Ast>], body: Ast) -> Result<(), String> {
+        self.enter_scope();
+
+        if let Some(name) = name {
+            self.symbol_table.define_function_name(&name);
+        }
+
+        let num_parameters = parameters.len();
+        let compiled = parameters.iter()
+            .try_for_each(|parameter| self.define(&parameter.inspect()).map(|_| ()))
+            .and_then(|_| self.compile(body));
+
+        if let Err(error) = compiled {
+            self.leave_scope();
+            return Err(error);
+        }
+
+        if self.last_instruction_is(Opcode::OpPop) {
+            self.replace_last_pop_with_return();
+        }
+        if !self.last_instruction_is(Opcode::OpReturnValue) {
+            self.emit(Opcode::OpReturn, vec![]);
+        }
+
+        let free_symbols = self.symbol_table.free_symbols.clone();
+        let num_locals = self.symbol_table.num_definitions;
+        let instructions = self.leave_scope();
+
+        check_limit(free_symbols.len(), MAX_U8_OPERAND, "free variables in closure")?;
+        for symbol in free_symbols.iter() {
+            self.capture_symbol(symbol.clone())?;
+        }
+
+        let function = CompiledFunction {
+            instructions,
+            num_locals,
+            num_parameters,
+        };
+        let constant = self.add_constant(Object::CompiledFunction { function: Rc::new(function) })?;
+        self.emit(Opcode::OpClosure, vec![constant, free_symbols.len()]);
+
+        Ok(())
+    }
+
+    pub fn bytecode(&self) -> Bytecode {
+        Bytecode {
+            instructions: self.scopes[0].instructions.clone(),
+            constants: self.constants.clone(),
+        }
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(CompilationScope::default());
+
+        let outer = mem::take(&mut self.symbol_table);
+        self.symbol_table = SymbolTable::new_enclosed(outer);
+    }
+
+    fn leave_scope(&mut self) -> Instructions {
+        let scope = self.scopes.pop().unwrap_or_default();
+
+        if let Some(outer) = self.symbol_table.outer.take() {
+            self.symbol_table = *outer;
+        }
+
+        scope.instructions
+    }
+
+    fn current_scope(&mut self) -> &mut CompilationScope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn current_instructions(&mut self) -> &mut Instructions {
+        &mut self.current_scope().instructions
+    }
+
+    fn load_symbol(&mut self, symbol: Symbol) -> Result<(), String> {
+        match symbol.scope {
+            SymbolScope::Global     => self.emit(Opcode::OpGetGlobal, vec![symbol.index]),
+            SymbolScope::Local      => self.emit(Opcode::OpGetLocal, vec![symbol.index]),
+            SymbolScope::Builtin    => self.emit(Opcode::OpGetBuiltin, vec![symbol.index]),
+            SymbolScope::Free       => {
+                check_limit(symbol.index + 1, MAX_U8_OPERAND, "free variables in closure")?;
+                self.emit(Opcode::OpGetFree, vec![symbol.index])
+            },
+            SymbolScope::Function   => self.emit(Opcode::OpCurrentClosure, vec![]),
+        };
+
+        Ok(())
+    }
+
+    // Closures capture locals and free variables by reference, as `eval`
+    // closes over its environment, so a later `let` of the same name in the
+    // enclosing function is visible inside them.
+    fn capture_symbol(&mut self, symbol: Symbol) -> Result<(), String> {
+        match symbol.scope {
+            SymbolScope::Local      => {
+                self.emit(Opcode::OpCaptureLocal, vec![symbol.index]);
+            },
+            SymbolScope::Free       => {
+                check_limit(symbol.index + 1, MAX_U8_OPERAND, "free variables in closure")?;
+                self.emit(Opcode::OpCaptureFree, vec![symbol.index]);
+            },
+            _                       => self.load_symbol(symbol)?,
+        }
+
+        Ok(())
+    }
+
+    // A function body may refer to a global that a later top-level `let`
+    // binds, as it can in `eval`, where the name is looked up only when the
+    // function runs. The global's slot is reserved here and filled by that
+    // `let`; outside a function the name is simply not defined yet.
+    fn define_top_level_name(&mut self, name: &str) -> Result<Symbol, String> {
+        if self.scopes.len() == 1 || !self.top_level_names.contains(name) {
+            return Err(format!("identifier not found: {}", name));
+        }
+
+        let symbol = self.symbol_table.define_global(name);
+        check_limit(symbol.index + 1, MAX_U16_OPERAND + 1, "global bindings")?;
+        Ok(symbol)
+    }
+
+    // Locals are addressed by a u8 operand, and the bytecode format stores
+    // CompiledFunction::num_locals in a u8, so a function may have at most
+    // 255 of them.
+    fn define(&mut self, name: &str) -> Result<Symbol, String> {
+        let symbol = self.symbol_table.define(name);
+        match symbol.scope {
+            SymbolScope::Global => check_limit(symbol.index + 1, MAX_U16_OPERAND + 1, "global bindings")?,
+            _                   => check_limit(symbol.index + 1, MAX_U8_OPERAND, "local bindings in function")?,
+        }
+        Ok(symbol)
+    }
+
+    // Equal integers and strings share one constant, so that a REPL session
+    // repeating the same literals does not keep growing the pool.
+    fn add_constant(&mut self, object: Object) -> Result<usize, String> {
+        let key = object.hash_key();
+        if let Some(index) = key.as_ref().and_then(|key| self.literals.get(key)) {
+            return Ok(*index);
+        }
+
+        check_limit(self.constants.len() + 1, MAX_U16_OPERAND + 1, "constants")?;
+        self.constants.push(object);
+        let index = self.constants.len() - 1;
+        if let Some(key) = key {
+            self.literals.insert(key, index);
+        }
+
+        Ok(index)
+    }
+
+    fn emit(&mut self, op: Opcode, operands: Vec<usize>) -> usize {
+        let instruction = make(op, operands);
+        let position = self.add_instruction(instruction);
+
+        self.set_last_instruction(op, position);
+
+        position
+    }
+
+    fn add_instruction(&mut self, mut instruction: Vec<u8>) -> usize {
+        let instructions = self.current_instructions();
+        let position = instructions.len();
+        instructions.append(&mut instruction);
+        position
+    }
+
+    fn set_last_instruction(&mut self, op: Opcode, position: usize) {
+        let scope = self.current_scope();
+        scope.previous_instruction = scope.last_instruction;
+        scope.last_instruction = Some(EmittedInstruction {
+            opcode: op,
+            position,
+        });
+    }
+
+    fn last_instruction_is(&self, op: Opcode) -> bool {
+        match self.scopes.last().and_then(|scope| scope.last_instruction) {
+            Some(last)  => last.opcode == op,
+            None        => false,
+        }
+    }
+
+    fn remove_last_pop(&mut self) {
+        let scope = self.current_scope();
+        if let Some(last) = scope.last_instruction {
+            scope.instructions.truncate(last.position);
+            scope.last_instruction = scope.previous_instruction;
+        }
+    }
+
+    fn replace_last_pop_with_return(&mut self) {
+        let scope = self.current_scope();
+        if let Some(last) = scope.last_instruction {
+            scope.instructions.truncate(last.position);
+        }
+
+        self.emit(Opcode::OpReturnValue, vec![]);
+    }
+
+    fn replace_instruction(&mut self, position: usize, new_instruction: Vec<u8>) {
+        let instructions = self.current_instructions();
+        for (i, byte) in new_instruction.into_iter().enumerate() {
+            instructions[position + i] = byte;
+        }
+    }
+
+    fn change_operand(&mut self, position: usize, operand: usize) -> Result<(), String> {
+        let op = match Opcode::from_byte(self.current_instructions()[position]) {
+            Some(value) => value,
+            None        => return Ok(()),
+        };
+        if operand > MAX_U16_OPERAND {
+            return Err(format!("jump target {} does not fit in 2 bytes: too much code in one function", operand));
+        }
+        let new_instruction = make(op, vec![operand]);
+
+        self.replace_instruction(position, new_instruction);
+        Ok(())
+    }
+}
+
+fn check_limit(count: usize, max: usize, what: &str) -> Result<(), String> {
+    if count > max {
+        return Err(format!("too many {}: at most {} are supported", what, max));
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use crate::token::{ Span };
+
+// Formats a message together with the source line it refers to, with the
+// spanned characters underlined:
+//
+//   parse error: expected next token to be Rparen, got Eof instead
+//    --> script.monkey:1:9
+//     |
+//   1 | add(1, 2
+//     |         ^
+pub fn render(heading: &str, span: Span, source: &str, origin: &str) -> String {
+    let line = source.lines().nth(span.line.saturating_sub(1)).unwrap_or("");
+    let line_number = span.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+
+    // Keep tabs in the padding so the carets line up however the terminal
+    // expands them.
+    let padding: String = line.chars()
+        .take(span.column.saturating_sub(1))
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    // A span running past the end of the line is underlined up to the end of it.
+    let remaining = line.chars().count().saturating_sub(span.column.saturating_sub(1));
+    let carets = "^".repeat(span.length.min(remaining).max(1));
+
+    format!("{}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+            heading,
+            gutter, origin, span.line, span.column,
+            gutter,
+            line_number, line,
+            gutter, padding, carets)
+}
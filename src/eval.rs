@@ -1,40 +1,255 @@
+use std::cell::{ Cell, RefCell };
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
 use crate::ast::{ Ast };
-use crate::object::{ Object };
+use crate::builtins;
+use crate::environment::{ Environment };
+use crate::object::{ HashKey, Object };
+use crate::token::{ Span };
+use crate::vm::{ MAX_FRAMES };
+
+thread_local! {
+    // Number of function calls currently being evaluated. Capped at the
+    // VM's frame limit, so both engines stop runaway recursion alike.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn eval(node: Ast, env: &Rc<RefCell<Environment>>) -> Object {
+    let span = node.span();
 
-pub fn eval(node: Ast) -> Object {
+    locate(eval_node(node, env), span)
+}
+
+fn eval_node(node: Ast, env: &Rc<RefCell<Environment>>) -> Object {
     match node {
-        Ast::Program { statements }                 => eval_statements(*statements).unwrap(),
-        Ast::ExpressionStatement { expression }     => eval(*expression),
-        Ast::Integer { value }                      => Object::Integer { value: value },
-        Ast::Boolean { value }                      => Object::Boolean { value: value },
-        Ast::PrefixExpression { operator, right}    => {
-            let _right = eval(*right);
-            return eval_prefix_expression(*operator, _right).unwrap();
+        Ast::Program { statements, .. }             => eval_program(*statements, env),
+        Ast::ExpressionStatement { expression, .. } => eval(*expression, env),
+        Ast::LetStatement { identifier, value, .. } => {
+            let _value = eval(*value, env);
+            if is_error_or_return(&_value) {
+                return _value;
+            }
+            if let Ast::Identifier { value: name, .. } = *identifier {
+                env.borrow_mut().set(*name, _value);
+            }
+            Object::Null
         },
-        Ast::InfixExpression { left, operator, right } => {
-            let _left = eval(*left);
-            let _right = eval(*right);
-            return eval_infix_expression(*operator, _left, _right);
+        Ast::ReturnStatement { return_value, .. }   => {
+            let value = eval(*return_value, env);
+            if is_error_or_return(&value) {
+                return value;
+            }
+            Object::ReturnValue { value: Box::new(value) }
+        },
+        Ast::Identifier { value, .. }               => eval_identifier(*value, env),
+        Ast::Integer { value, .. }                  => Object::Integer { value },
+        Ast::Boolean { value, .. }                  => Object::Boolean { value },
+        Ast::StringLiteral { value, .. }            => Object::String { value: *value },
+        Ast::PrefixExpression { operator, right, .. } => {
+            let _right = eval(*right, env);
+            if is_error_or_return(&_right) {
+                return _right;
+            }
+            eval_prefix_expression(*operator, _right)
+        },
+        Ast::InfixExpression { left, operator, right, .. } => {
+            let _left = eval(*left, env);
+            if is_error_or_return(&_left) {
+                return _left;
+            }
+            let _right = eval(*right, env);
+            if is_error_or_return(&_right) {
+                return _right;
+            }
+            eval_infix_expression(*operator, _left, _right)
+        },
+        Ast::IfExpression { condition, body, alternative, .. } => {
+            let _condition = eval(*condition, env);
+            if is_error_or_return(&_condition) {
+                return _condition;
+            }
+
+            if is_truthy(&_condition) {
+                return eval(*body, env);
+            }
+
+            match alternative {
+                Some(value) => eval(*value, env),
+                None        => Object::Null,
+            }
+        },
+        Ast::BlockStatement { statements, .. }      => eval_block_statement(*statements, env),
+        Ast::FunctionLiteral { arguments, body, .. } => Object::Function {
+            parameters: *arguments,
+            body,
+            env: Rc::clone(env),
+        },
+        Ast::CallExpression { function, arguments, .. } => {
+            let _function = eval(*function, env);
+            if is_error_or_return(&_function) {
+                return _function;
+            }
+            let _arguments = match eval_expressions(*arguments, env) {
+                Ok(value)   => value,
+                Err(error)  => return error,
+            };
+            apply_function(_function, _arguments)
+        },
+        Ast::ArrayLiteral { elements, .. }          => {
+            match eval_expressions(*elements, env) {
+                Ok(value)   => Object::Array { elements: value },
+                Err(error)  => error,
+            }
+        },
+        Ast::HashLiteral { pairs, .. }              => eval_hash_literal(*pairs, env),
+        Ast::IndexExpression { left, index, .. }    => {
+            let _left = eval(*left, env);
+            if is_error_or_return(&_left) {
+                return _left;
+            }
+            let _index = eval(*index, env);
+            if is_error_or_return(&_index) {
+                return _index;
+            }
+            eval_index_expression(_left, _index)
         },
-        _   => Object::Null,
     }
 }
 
-pub fn eval_statements(statements: Vec<Box<Ast>>) -> Option<Object> {
+pub fn eval_program(statements: Vec<Box<Ast>>, env: &Rc<RefCell<Environment>>) -> Object {
     let mut result = Object::Null;
 
     for statement in statements {
-        result = eval(*statement);
+        result = eval(*statement, env);
+
+        match result {
+            Object::ReturnValue { value }   => return *value,
+            Object::Error { .. }            => return result,
+            _                               => (),
+        }
+    }
+
+    result
+}
+
+// Unlike eval_program, a ReturnValue is passed up unwrapped so that it
+// unwinds every enclosing block until it reaches the function boundary.
+pub fn eval_block_statement(statements: Vec<Box<Ast>>, env: &Rc<RefCell<Environment>>) -> Object {
+    let mut result = Object::Null;
+
+    for statement in statements {
+        result = eval(*statement, env);
+
+        match result {
+            Object::ReturnValue { .. }  |
+            Object::Error { .. }        => return result,
+            _                           => (),
+        }
+    }
+
+    result
+}
+
+pub fn eval_expressions(expressions: Vec<Box<Ast>>, env: &Rc<RefCell<Environment>>) -> Result<Vec<Object>, Object> {
+    let mut result = Vec::new();
+
+    for expression in expressions {
+        let evaluated = eval(*expression, env);
+        if is_error_or_return(&evaluated) {
+            return Err(evaluated);
+        }
+        result.push(evaluated);
+    }
+
+    Ok(result)
+}
+
+pub fn eval_hash_literal(pairs: Vec<(Box<Ast>, Box<Ast>)>, env: &Rc<RefCell<Environment>>) -> Object {
+    let mut result = BTreeMap::new();
+
+    for (key, value) in pairs {
+        let _key = eval(*key, env);
+        if is_error_or_return(&_key) {
+            return _key;
+        }
+
+        let hash_key = match _key.hash_key() {
+            Some(value) => value,
+            None        => return new_error(format!("unusable as hash key: {}", _key.kind())),
+        };
+
+        let _value = eval(*value, env);
+        if is_error_or_return(&_value) {
+            return _value;
+        }
+
+        result.insert(hash_key, _value);
+    }
+
+    Object::Hash { pairs: result }
+}
+
+pub fn apply_function(function: Object, arguments: Vec<Object>) -> Object {
+    match function {
+        Object::Function { parameters, body, env } => {
+            if parameters.len() != arguments.len() {
+                return new_error(format!("wrong number of arguments: want={}, got={}",
+                                         parameters.len(), arguments.len()));
+            }
+
+            // The VM's main frame counts against MAX_FRAMES too.
+            let depth = DEPTH.with(|depth| depth.get());
+            if depth + 1 >= MAX_FRAMES {
+                return new_error("stack overflow".to_string());
+            }
+
+            let extended_env = extend_function_env(&parameters, arguments, env);
+            DEPTH.with(|d| d.set(depth + 1));
+            let result = eval(*body, &extended_env);
+            DEPTH.with(|d| d.set(depth));
+            unwrap_return_value(result)
+        },
+        Object::Builtin { function }    => function(arguments),
+        _   => new_error(format!("not a function: {}", function.kind())),
+    }
+}
+
+fn unwrap_return_value(object: Object) -> Object {
+    match object {
+        Object::ReturnValue { value }   => *value,
+        _                               => object,
+    }
+}
+
+fn extend_function_env(parameters: &[Box<Ast>], arguments: Vec<Object>, env: Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+    let mut extended = Environment::new_enclosed(env);
+
+    for (parameter, argument) in parameters.iter().zip(arguments) {
+        if let Ast::Identifier { value, .. } = parameter.as_ref() {
+            extended.set(value.to_string(), argument);
+        }
     }
 
-    Some(result)
+    Rc::new(RefCell::new(extended))
 }
 
-pub fn eval_prefix_expression(operator: String, right: Object) -> Option<Object> {
+pub fn eval_identifier(name: String, env: &Rc<RefCell<Environment>>) -> Object {
+    if let Some(value) = env.borrow().get(&name) {
+        return value;
+    }
+
+    match builtins::lookup(&name) {
+        Some(value) => value,
+        None        => new_error(format!("identifier not found: {}", name)),
+    }
+}
+
+pub fn eval_prefix_expression(operator: String, right: Object) -> Object {
     match operator.as_ref() {
-        "!" => Some(eval_bang_operator_expression(right)),
-        "-" => Some(eval_minus_prefix_operator_expression(right)),
-        _   => None,
+        "!" => eval_bang_operator_expression(right),
+        "-" => eval_minus_prefix_operator_expression(right),
+        _   => new_error(format!("unknown operator: {}{}", operator, right.kind())),
     }
 }
 
@@ -44,7 +259,7 @@ pub fn eval_bang_operator_expression(right: Object) -> Object {
             if value {
                 return Object::Boolean { value: false };
             }
-            return Object::Boolean { value: true };
+            Object::Boolean { value: true }
         },
         Object::Null    => Object::Boolean { value: true },
         _               => Object::Boolean { value: false },
@@ -53,45 +268,130 @@ pub fn eval_bang_operator_expression(right: Object) -> Object {
 
 pub fn eval_minus_prefix_operator_expression(right: Object) -> Object {
     match right {
-        Object::Integer { value }   => Object::Integer { value: -value },
-        _                           => Object::Null,
+        Object::Integer { value }   => match value.checked_neg() {
+            Some(value) => Object::Integer { value },
+            None        => new_error(format!("integer overflow: -{}", value)),
+        },
+        _                           => new_error(format!("unknown operator: -{}", right.kind())),
     }
 }
 
 pub fn eval_infix_expression(operator: String, left: Object, right: Object) -> Object {
-    if let Object::Integer { value: lvalue } = left {
-        if let Object::Integer { value: rvalue } = right {
-            return eval_integer_infix_expression(operator, lvalue, rvalue)
-        }
+    match (&left, &right) {
+        (Object::Integer { value: lvalue }, Object::Integer { value: rvalue }) => {
+            return eval_integer_infix_expression(operator, *lvalue, *rvalue);
+        },
+        (Object::Boolean { value: lvalue }, Object::Boolean { value: rvalue }) => {
+            return eval_boolean_infix_expression(operator, *lvalue, *rvalue);
+        },
+        (Object::String { value: lvalue }, Object::String { value: rvalue }) => {
+            return eval_string_infix_expression(operator, lvalue, rvalue);
+        },
+        _ => (),
     }
 
-    if let Object::Boolean { value: lvalue } = left {
-        if let Object::Boolean { value: rvalue } = right {
-            return eval_boolean_infix_expression(operator, lvalue, rvalue)
-        }
+    if left.kind() != right.kind() {
+        return new_error(format!("type mismatch: {} {} {}", left.kind(), operator, right.kind()));
     }
 
-    Object::Null
+    new_error(format!("unknown operator: {} {} {}", left.kind(), operator, right.kind()))
 }
 
 pub fn eval_integer_infix_expression(operator: String, lvalue: i64, rvalue: i64) -> Object {
+    let value = match operator.as_ref() {
+        "+"     => lvalue.checked_add(rvalue),
+        "-"     => lvalue.checked_sub(rvalue),
+        "*"     => lvalue.checked_mul(rvalue),
+        "/"     => {
+            if rvalue == 0 {
+                return new_error(format!("division by zero: {} / {}", lvalue, rvalue));
+            }
+            lvalue.checked_div(rvalue)
+        },
+        "<"     => return Object::Boolean { value: lvalue < rvalue },
+        ">"     => return Object::Boolean { value: lvalue > rvalue },
+        "=="    => return Object::Boolean { value: lvalue == rvalue },
+        "!="    => return Object::Boolean { value: lvalue != rvalue },
+        _       => return new_error(format!("unknown operator: Integer {} Integer", operator)),
+    };
+
+    match value {
+        Some(value) => Object::Integer { value },
+        None        => new_error(format!("integer overflow: {} {} {}", lvalue, operator, rvalue)),
+    }
+}
+
+pub fn eval_boolean_infix_expression(operator: String, lvalue: bool, rvalue: bool) -> Object {
     match operator.as_ref() {
-        "+"     => Object::Integer { value: lvalue + rvalue },
-        "-"     => Object::Integer { value: lvalue - rvalue },
-        "*"     => Object::Integer { value: lvalue * rvalue },
-        "/"     => Object::Integer { value: lvalue / rvalue },
-        "<"     => Object::Boolean { value: lvalue < rvalue },
-        ">"     => Object::Boolean { value: lvalue > rvalue },
         "=="    => Object::Boolean { value: lvalue == rvalue },
         "!="    => Object::Boolean { value: lvalue != rvalue },
-        _       => Object::Null,
+        _       => new_error(format!("unknown operator: Boolean {} Boolean", operator)),
     }
 }
 
-pub fn eval_boolean_infix_expression(operator: String, lvalue: bool, rvalue: bool) -> Object {
+pub fn eval_string_infix_expression(operator: String, lvalue: &str, rvalue: &str) -> Object {
     match operator.as_ref() {
+        "+"     => Object::String { value: format!("{}{}", lvalue, rvalue) },
         "=="    => Object::Boolean { value: lvalue == rvalue },
         "!="    => Object::Boolean { value: lvalue != rvalue },
-        _       => Object::Null,
+        _       => new_error(format!("unknown operator: String {} String", operator)),
+    }
+}
+
+pub fn eval_index_expression(left: Object, index: Object) -> Object {
+    match (&left, &index) {
+        (Object::Array { elements }, Object::Integer { value }) => eval_array_index_expression(elements, *value),
+        (Object::Hash { pairs }, _)                             => eval_hash_index_expression(pairs, &index),
+        _   => new_error(format!("index operator not supported: {}[{}]", left.kind(), index.kind())),
+    }
+}
+
+// Negative indices count back from the end of the array, so `arr[-1]` is the
+// last element.
+pub fn eval_array_index_expression(elements: &[Object], index: i64) -> Object {
+    let length = elements.len() as i64;
+    let position = if index < 0 { index + length } else { index };
+
+    if position < 0 || position >= length {
+        return new_error(format!("index out of bounds: index={}, length={}", index, length));
+    }
+
+    elements[position as usize].clone()
+}
+
+pub fn eval_hash_index_expression(pairs: &BTreeMap<HashKey, Object>, index: &Object) -> Object {
+    match index.hash_key() {
+        Some(key)   => match pairs.get(&key) {
+            Some(value) => value.clone(),
+            None        => Object::Null,
+        },
+        None        => new_error(format!("unusable as hash key: {}", index.kind())),
+    }
+}
+
+pub fn is_truthy(object: &Object) -> bool {
+    match object {
+        Object::Boolean { value }   => *value,
+        Object::Null                => false,
+        _                           => true,
+    }
+}
+
+fn new_error(message: String) -> Object {
+    Object::Error { message, span: None }
+}
+
+// An error is located at the innermost node it came out of: the operator,
+// call or index it arose from, or the identifier that was not found.
+fn locate(object: Object, span: Span) -> Object {
+    match object {
+        Object::Error { message, span: None }   => Object::Error { message, span: Some(span) },
+        _                                       => object,
     }
-}
\ No newline at end of file
+}
+
+// A ReturnValue from an `if` used as a value has to unwind just like an
+// error, or the enclosing expression would swallow it.
+fn is_error_or_return(object: &Object) -> bool {
+    matches!(object, Object::Error { .. } | Object::ReturnValue { .. })
+}
@@ -1,19 +1,25 @@
-use crate::token::{ Token, TokenKind };
+use crate::token::{ Span, Token, TokenKind };
 
 pub struct Lexer {
-    input: String,
+    input: Vec<char>,
     position: usize,
     read_position: usize,
     ch: char,
+    offset: usize,      // Byte offset, line and column of self.position
+    line: usize,
+    column: usize,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
         let mut lexer = Lexer {
-            input: input,
+            input: input.chars().collect(),
             position: 0,
             read_position: 0,
             ch: '\0',
+            offset: 0,
+            line: 1,
+            column: 1,
         };
 
         lexer.read_char();
@@ -21,74 +27,91 @@ impl Lexer {
     }
     
     pub fn next_token(&mut self) -> Token {
-        let token: Token;
         self.skip();
 
+        let start = self.position;
+        let mut span = Span {
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+            length: 0,
+        };
+
+        let (kind, literal) = self.read_token();
+        span.length = self.position - start;
+
+        Token { kind, literal, span }
+    }
+
+    fn read_token(&mut self) -> (TokenKind, String) {
+        let token: (TokenKind, String);
+
         match self.ch {
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
-                    token = Token { kind: TokenKind::Eq, literal: "==".to_string() };
+                    token = (TokenKind::Eq, "==".to_string());
                 }
                 else {
-                    token = Token { kind: TokenKind::Assign, literal: self.ch.to_string() };
+                    token = (TokenKind::Assign, self.ch.to_string());
                 }
             },
-            '+' => token = Token { kind: TokenKind::Plus, literal: self.ch.to_string() },
-            '-' => token = Token { kind: TokenKind::Minus, literal: self.ch.to_string() },
-            '*' => token = Token { kind: TokenKind::Asterisk, literal: self.ch.to_string() },
-            '/' => token = Token { kind: TokenKind::Slash, literal: self.ch.to_string() },
+            '+' => token = (TokenKind::Plus, self.ch.to_string()),
+            '-' => token = (TokenKind::Minus, self.ch.to_string()),
+            '*' => token = (TokenKind::Asterisk, self.ch.to_string()),
+            '/' => token = (TokenKind::Slash, self.ch.to_string()),
             '!' =>  {
                 if self.peek_char() == '=' {
                     self.read_char();
-                    token = Token { kind: TokenKind::NotEq, literal: "!=".to_string() };
+                    token = (TokenKind::NotEq, "!=".to_string());
                 }
                 else {
-                    token = Token { kind: TokenKind::Bang, literal: self.ch.to_string() };
+                    token = (TokenKind::Bang, self.ch.to_string());
                 }
             },
-            '<' => token = Token { kind: TokenKind::Lt, literal: self.ch.to_string() },
-            '>' => token = Token { kind: TokenKind::Gt, literal: self.ch.to_string() },
-            ';' => token = Token { kind: TokenKind::Semicolon, literal: self.ch.to_string() },
-            ',' => token = Token { kind: TokenKind::Comma, literal: self.ch.to_string() },
-            '(' => token = Token { kind: TokenKind::Lparen, literal: self.ch.to_string() },
-            ')' => token = Token { kind: TokenKind::Rparen, literal: self.ch.to_string() },
-            '{' => token = Token { kind: TokenKind::Lbrace, literal: self.ch.to_string() },
-            '}' => token = Token { kind: TokenKind::Rbrace, literal: self.ch.to_string() },
-            '[' => token = Token { kind: TokenKind::Lbracket, literal: self.ch.to_string() },
-            ']' => token = Token { kind: TokenKind::Rbracket, literal: self.ch.to_string() },
-            '0' ... '9' => return Token { kind: TokenKind::Integer, literal: self.read_integer() },
-            'a' ... 'z' |
-            'A' ... 'Z' |
+            '<' => token = (TokenKind::Lt, self.ch.to_string()),
+            '>' => token = (TokenKind::Gt, self.ch.to_string()),
+            ';' => token = (TokenKind::Semicolon, self.ch.to_string()),
+            ':' => token = (TokenKind::Colon, self.ch.to_string()),
+            ',' => token = (TokenKind::Comma, self.ch.to_string()),
+            '(' => token = (TokenKind::Lparen, self.ch.to_string()),
+            ')' => token = (TokenKind::Rparen, self.ch.to_string()),
+            '{' => token = (TokenKind::Lbrace, self.ch.to_string()),
+            '}' => token = (TokenKind::Rbrace, self.ch.to_string()),
+            '[' => token = (TokenKind::Lbracket, self.ch.to_string()),
+            ']' => token = (TokenKind::Rbracket, self.ch.to_string()),
+            '0' ..= '9' => return (TokenKind::Integer, self.read_integer()),
+            'a' ..= 'z' |
+            'A' ..= 'Z' |
             '_'  => {
                 let ident = self.read_identifier();
                 if ident == "let" {
-                    return Token { kind: TokenKind::Let, literal: ident };
+                    return (TokenKind::Let, ident);
                 }
                 else if ident == "fn" {
-                    return Token { kind: TokenKind::Function, literal: ident };
+                    return (TokenKind::Function, ident);
                 }
                 else if ident == "if" {
-                    return Token { kind: TokenKind::If, literal: ident };
+                    return (TokenKind::If, ident);
                 }
                 else if ident == "else" {
-                    return Token { kind: TokenKind::Else, literal: ident };
+                    return (TokenKind::Else, ident);
                 }
                 else if ident == "return" {
-                    return Token { kind: TokenKind::Return, literal: ident };
+                    return (TokenKind::Return, ident);
                 }
                 else if ident == "true" {
-                    return Token { kind: TokenKind::True, literal: ident };
+                    return (TokenKind::True, ident);
                 }
                 else if ident == "false" {
-                    return Token { kind: TokenKind::False, literal: ident };
+                    return (TokenKind::False, ident);
                 }
 
-                return Token { kind: TokenKind::Identifier, literal: ident }
+                return (TokenKind::Identifier, ident)
             },
-            '"'  => token = Token { kind: TokenKind::String, literal: self.read_string() },
-            '\0' => return  Token { kind: TokenKind::Eof, literal: self.ch.to_string() },
-            _ => token = Token { kind: TokenKind::Illegal, literal: self.ch.to_string() },
+            '"'  => token = (TokenKind::String, self.read_string()),
+            '\0' => return (TokenKind::Eof, self.ch.to_string()),
+            _ => token = (TokenKind::Illegal, self.ch.to_string()),
         }
         
         self.read_char();
@@ -102,20 +125,29 @@ impl Lexer {
     }
 
     fn peek_char(&self) -> char {
-        if self.read_position + 1 >= self.input.len() {
+        if self.read_position >= self.input.len() {
             return '\0';
         }
-        self.input.chars()
-            .skip(self.read_position).next().unwrap()
+        self.input[self.read_position]
     }
     
     pub fn read_char(&mut self) {
+        if self.read_position > 0 && self.position < self.input.len() {
+            self.offset += self.ch.len_utf8();
+            if self.ch == '\n' {
+                self.line += 1;
+                self.column = 1;
+            }
+            else {
+                self.column += 1;
+            }
+        }
+
         if self.read_position >= self.input.len() {
             self.ch = '\0';
         }
         else {
-            self.ch = self.input.chars()
-                .skip(self.read_position).next().unwrap();
+            self.ch = self.input[self.read_position];
         }
 
         self.position = self.read_position;
@@ -125,11 +157,11 @@ impl Lexer {
     fn read_integer(&mut self) -> String {
         let start = self.position;
         
-        while self.ch.is_digit(10) {
+        while self.ch.is_ascii_digit() {
             self.read_char();
         }
 
-        self.input[start..self.position].to_string()
+        self.input[start..self.position].iter().collect()
     }
 
     fn read_identifier(&mut self) -> String {
@@ -139,20 +171,39 @@ impl Lexer {
             self.read_char();
         }
 
-        self.input[start..self.position].to_string()
+        self.input[start..self.position].iter().collect()
     }
 
     fn read_string(&mut self) -> String {
-        let start = self.position + 1;
+        let mut string = String::new();
         self.read_char();
-        
+
         loop {
-            if self.ch == '"' || self.ch == '\0' {
-                break;
+            match self.ch {
+                '"' | '\0' => break,
+                '\\'       => {
+                    self.read_char();
+                    match self.ch {
+                        'n'  => string.push('\n'),
+                        't'  => string.push('\t'),
+                        'r'  => string.push('\r'),
+                        '"'  => string.push('"'),
+                        '\\' => string.push('\\'),
+                        '\0' => {
+                            string.push('\\');
+                            break;
+                        },
+                        _    => {
+                            string.push('\\');
+                            string.push(self.ch);
+                        },
+                    }
+                },
+                _           => string.push(self.ch),
             }
             self.read_char();
         }
-                
-        self.input[start..self.position].to_string()
+
+        string
     }
 }
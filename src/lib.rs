@@ -5,4 +5,12 @@ pub mod repl;
 pub mod token;
 pub mod lexer;
 pub mod object;
-pub mod eval;
\ No newline at end of file
+pub mod environment;
+pub mod builtins;
+pub mod eval;
+pub mod symbol_table;
+pub mod compiler;
+pub mod vm;
+pub mod serialize;
+pub mod diagnostic;
+pub mod cli;
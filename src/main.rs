@@ -1,5 +1,24 @@
-use simiaVM::repl::{ start };
+use std::env;
+use std::panic;
+use std::process;
+use std::thread;
+
+use simia_vm::cli::{ run };
+
+// The tree-walking evaluator recurses on the native stack, several Rust
+// frames per Monkey call, so it needs far more than the default to reach
+// the call depth limit it shares with the VM.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
-    start();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let code = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&args))
+        .expect("failed to start the interpreter thread")
+        .join()
+        .unwrap_or_else(|payload| panic::resume_unwind(payload));
+
+    process::exit(code);
 }
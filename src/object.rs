@@ -1,3 +1,21 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::ast::{ Ast };
+use crate::code::{ Instructions };
+use crate::environment::{ Environment };
+use crate::token::{ Span };
+
+pub type BuiltinFunction = fn(Vec<Object>) -> Object;
+
+pub struct CompiledFunction {
+    pub instructions: Instructions,
+    pub num_locals: usize,
+    pub num_parameters: usize,
+}
+
+#[derive(Clone)]
 pub enum Object {
     Integer {
         value: i64,
@@ -7,6 +25,52 @@ pub enum Object {
         value: bool,
     },
 
+    String {
+        value: String,
+    },
+
+    Array {
+        elements: Vec<Object>,
+    },
+
+    Hash {
+        pairs: BTreeMap<HashKey, Object>,
+    },
+
+    ReturnValue {
+        value: Box<Object>,
+    },
+
+    Function {
+        parameters: Vec<Box<Ast>>,        // Identifier List
+        body: Box<Ast>,                   // Block Statement
+        env: Rc<RefCell<Environment>>,
+    },
+
+    Builtin {
+        function: BuiltinFunction,
+    },
+
+    CompiledFunction {
+        function: Rc<CompiledFunction>,
+    },
+
+    Closure {
+        function: Rc<CompiledFunction>,
+        free: Vec<Rc<RefCell<Object>>>,   // captured free variables, shared with the frame they came from
+    },
+
+    // A local that a closure has captured. It only ever sits in a VM stack
+    // slot, so that the frame and the closure see the same binding.
+    Cell {
+        value: Rc<RefCell<Object>>,
+    },
+
+    Error {
+        message: String,
+        span: Option<Span>,               // where `eval` raised it; the VM knows no source positions
+    },
+
     Null,
 }
 
@@ -15,8 +79,32 @@ impl Object {
         match self {
             Object::Integer { value }   => format!("{}", value),
             Object::Boolean { value }   => format!("{}", value),
-            Object::Null                => format!("null"),
-            _                           => panic!()
+            Object::String { value }    => value.to_string(),
+            Object::Array { elements }  => {
+                let elements: Vec<String> = elements.iter().map(|e| e.inspect()).collect();
+                format!("[{}]", elements.join(", "))
+            },
+            Object::Hash { pairs }      => {
+                let pairs: Vec<String> = pairs.iter()
+                    .map(|(k, v)| format!("{}: {}", k.inspect(), v.inspect()))
+                    .collect();
+                format!("{{{}}}", pairs.join(", "))
+            },
+            Object::ReturnValue { value }   => value.inspect(),
+            Object::Function { parameters, body, .. } => {
+                let parameters: Vec<String> = parameters.iter().map(|p| p.inspect()).collect();
+                format!("fn({}){{ {} }}", parameters.join(","), body.inspect())
+            },
+            Object::Builtin { .. }      => "builtin function".to_string(),
+            Object::CompiledFunction { function } => {
+                format!("compiled function[{} params, {} locals]", function.num_parameters, function.num_locals)
+            },
+            Object::Closure { function, .. } => {
+                format!("closure[{} params, {} locals]", function.num_parameters, function.num_locals)
+            },
+            Object::Cell { value }      => value.borrow().inspect(),
+            Object::Error { message, .. }   => format!("ERROR: {}", message),
+            Object::Null                => "null".to_string(),
         }
     }
 
@@ -24,8 +112,59 @@ impl Object {
         match self {
             Object::Integer { value: _ }   => "Integer".to_string(),
             Object::Boolean { value: _ }   => "Boolean".to_string(),
+            Object::String { value: _ }    => "String".to_string(),
+            Object::Array { .. }           => "Array".to_string(),
+            Object::Hash { .. }            => "Hash".to_string(),
+            Object::ReturnValue { .. }     => "ReturnValue".to_string(),
+            Object::Function { .. }        => "Function".to_string(),
+            Object::Builtin { .. }         => "Builtin".to_string(),
+            Object::CompiledFunction { .. } => "CompiledFunction".to_string(),
+            // A closure is what a function literal evaluates to on the VM,
+            // so errors name it the way `eval` does.
+            Object::Closure { .. }         => "Function".to_string(),
+            Object::Cell { .. }            => "Cell".to_string(),
+            Object::Error { .. }           => "Error".to_string(),
             Object::Null                => "Null".to_string(),
-            _                           => panic!()
         }
     }
-}
\ No newline at end of file
+
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer { value }   => Some(HashKey::Integer { value: *value }),
+            Object::Boolean { value }   => Some(HashKey::Boolean { value: *value }),
+            Object::String { value }    => Some(HashKey::String { value: value.to_string() }),
+            _                           => None,
+        }
+    }
+}
+
+// Key of an `Object::Hash`. Only Integer, Boolean and String objects can be
+// used as keys; `Object::hash_key` performs the conversion.
+#[derive(Clone,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub enum HashKey {
+    Integer {
+        value: i64,
+    },
+
+    Boolean {
+        value: bool,
+    },
+
+    String {
+        value: String,
+    },
+}
+
+impl HashKey {
+    pub fn inspect(&self) -> String {
+        self.object().inspect()
+    }
+
+    pub fn object(&self) -> Object {
+        match self {
+            HashKey::Integer { value }  => Object::Integer { value: *value },
+            HashKey::Boolean { value }  => Object::Boolean { value: *value },
+            HashKey::String { value }   => Object::String { value: value.to_string() },
+        }
+    }
+}
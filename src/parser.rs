@@ -1,6 +1,58 @@
+use std::fmt;
+
 use crate::ast::{ Ast };
 use crate::lexer::{ Lexer };
-use crate::token::{ Token, TokenKind };
+use crate::token::{ Span, Token, TokenKind };
+
+#[derive(Clone,PartialEq,Debug)]
+pub enum ParseError {
+    ExpectedToken {
+        expected: TokenKind,
+        found: Token,
+    },
+
+    NoPrefixParseFn {
+        found: Token,
+    },
+
+    InvalidInteger {
+        found: Token,
+    },
+
+    IllegalToken {
+        found: Token,
+    },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::ExpectedToken { found, .. } |
+            ParseError::NoPrefixParseFn { found }   |
+            ParseError::InvalidInteger { found }    |
+            ParseError::IllegalToken { found }      => found.span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedToken { expected, found }   => {
+                write!(f, "expected next token to be {}, got {} instead", expected.literal(), found.kind.literal())
+            },
+            ParseError::NoPrefixParseFn { found }           => {
+                write!(f, "no prefix parse function for {} found", found.kind.literal())
+            },
+            ParseError::InvalidInteger { found }            => {
+                write!(f, "could not parse {} as integer", found.literal)
+            },
+            ParseError::IllegalToken { found }              => {
+                write!(f, "illegal character {:?}", found.literal)
+            },
+        }
+    }
+}
 
 #[derive(PartialEq,Clone)]
 enum Precedence {
@@ -35,15 +87,17 @@ pub struct Parser {
     lexer: Lexer,
     cur_token: Token,
     peek_token: Token,
-    pub errors: Vec<String>,
+    brackets: Vec<TokenKind>,   // Brackets opened and not yet closed before cur_token
+    pub errors: Vec<ParseError>,
 }
 
 impl Parser {
     pub fn new(lexer: Lexer) -> Parser {
         let mut parser = Parser {
-            lexer: lexer,
-            cur_token:  Token { kind: TokenKind::Illegal, literal: "".to_string() },
-            peek_token: Token { kind: TokenKind::Illegal, literal: "".to_string() },
+            lexer,
+            cur_token:  Token { kind: TokenKind::Illegal, literal: "".to_string(), span: Span::default() },
+            peek_token: Token { kind: TokenKind::Illegal, literal: "".to_string(), span: Span::default() },
+            brackets: Vec::new(),
             errors: Vec::new(),
         };
 
@@ -54,21 +108,43 @@ impl Parser {
     }
 
     fn next_token(&mut self) {
+        // A closing bracket also closes any brackets left open inside it.
+        let opener = match self.cur_token.kind {
+            TokenKind::Lparen   |
+            TokenKind::Lbrace   |
+            TokenKind::Lbracket => {
+                self.brackets.push(self.cur_token.kind.clone());
+                None
+            },
+            TokenKind::Rparen   => Some(TokenKind::Lparen),
+            TokenKind::Rbrace   => Some(TokenKind::Lbrace),
+            TokenKind::Rbracket => Some(TokenKind::Lbracket),
+            _                   => None,
+        };
+
+        if let Some(opener) = opener {
+            if let Some(index) = self.brackets.iter().rposition(|kind| *kind == opener) {
+                self.brackets.truncate(index);
+            }
+        }
+
         self.cur_token = self.peek_token.clone();
         self.peek_token = self.lexer.next_token();
     }
     
     pub fn parse_program(&mut self) -> Option<Ast> {
-        let mut program = Ast::Program { statements: Box::new(Vec::new()) };
+        let mut program = Ast::Program { statements: Box::new(Vec::new()), span: self.cur_token.span };
 
         while !self.cur_token_is(TokenKind::Eof) {
+            let start = (self.cur_token.span, self.brackets.len());
+
             if let Some(value) = self.parse_statement() {
-                if let Ast::Program { ref mut statements } = program {
+                if let Ast::Program { ref mut statements, .. } = program {
                     statements.push(Box::new(value));
                 }
             }
             else {
-                self.next_token();
+                self.synchronize(start);
                 continue;
             }
             self.next_token();
@@ -77,6 +153,38 @@ impl Parser {
         Some(program)
     }
 
+    // Panic-mode recovery after a statement that started at `start` failed to
+    // parse: skip to the next token a statement can begin at, so one mistake
+    // is reported once. A `}` only counts if no block was opened since the
+    // statement began, so one inside a half-parsed function literal does not
+    // end it early. A `;` additionally waits for the `(` and `[` that were
+    // open at the mistake to be closed, unless it ends the line.
+    fn synchronize(&mut self, start: (Span, usize)) {
+        let (span, depth) = start;
+
+        if self.cur_token.span == span {
+            self.next_token();
+        }
+
+        loop {
+            let opened = &self.brackets[depth.min(self.brackets.len())..];
+            let in_new_block = opened.contains(&TokenKind::Lbrace);
+            let ends_statement = opened.is_empty() || self.peek_token.span.line > self.cur_token.span.line;
+
+            match self.cur_token.kind {
+                TokenKind::Eof      |
+                TokenKind::Let      |
+                TokenKind::Return                                       => return,
+                TokenKind::Rbrace if !in_new_block                      => return,
+                TokenKind::Semicolon if !in_new_block && ends_statement => {
+                    self.next_token();
+                    return;
+                },
+                _                                                       => self.next_token(),
+            }
+        }
+    }
+
     fn parse_statement(&mut self) -> Option<Ast> {
         match self.cur_token.kind {
             TokenKind::Let    => self.parse_let_statement(),
@@ -86,12 +194,15 @@ impl Parser {
     }
 
     fn parse_let_statement(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
+
         if !self.expect_peek(TokenKind::Identifier) {
             return None;
         }
 
         let identifier = Box::new(Ast::Identifier {
             value: Box::new(self.cur_token.clone().literal()),
+            span: self.cur_token.span,
         });
 
         if !self.expect_peek(TokenKind::Assign) {
@@ -110,12 +221,15 @@ impl Parser {
         }
         
         Some(Ast::LetStatement {
-            identifier: identifier,
-            value: value,
+            identifier,
+            value,
+            span,
         })
     }
 
     fn parse_return_statement(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
+
         self.next_token();
 
         let return_value = match self.parse_expression(Precedence::Lowest) {
@@ -128,11 +242,14 @@ impl Parser {
         }
 
         Some(Ast::ReturnStatement {
-            return_value: return_value,
+            return_value,
+            span,
         })
     }
     
     fn parse_expression_statement(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
+
         let expression = match self.parse_expression(Precedence::Lowest) {
             Some(value) => Box::new(value),
             None        => {
@@ -144,7 +261,7 @@ impl Parser {
             self.next_token();
         }
 
-        Some(Ast::ExpressionStatement { expression: expression })
+        Some(Ast::ExpressionStatement { expression, span })
     }
 
     fn parse_expression(&mut self, precedence: Precedence) -> Option<Ast> {
@@ -159,14 +276,18 @@ impl Parser {
             TokenKind::Lparen     => self.parse_grouped_expression(),
             TokenKind::If         => self.parse_if_expression(),
             TokenKind::Function   => self.parse_function_literal(),
-            TokenKind::Lbrace     => self.parse_block_statement(),
+            TokenKind::Lbrace     => self.parse_hash_literal(),
             TokenKind::Lbracket   => self.parse_array_literal(),
             _                     => {
-                self.no_prefix_parse_fn_error(self.cur_token.kind.clone());
+                self.no_prefix_parse_fn_error();
                 return None;
             },
         };
 
+        // The prefix parser has recorded why it failed; there is nothing to
+        // apply an infix operator to.
+        left_exp.as_ref()?;
+
         while !self.peek_token_is(TokenKind::Semicolon) && (precedence.clone() as u8) < (self.peek_precedence() as u8) {
             left_exp = match self.peek_token.kind {
                 TokenKind::Plus     |
@@ -178,7 +299,7 @@ impl Parser {
                 TokenKind::Gt       |
                 TokenKind::NotEq    => self.parse_infix_expression(left_exp),
                 TokenKind::Lparen   => self.parse_call_expression(left_exp),
-                TokenKind::Lbrace   => self.parse_index_expression(left_exp),
+                TokenKind::Lbracket => self.parse_index_expression(left_exp),
                 _ =>  return left_exp,
             };
         }
@@ -187,27 +308,31 @@ impl Parser {
     }
 
     fn parse_identifier(&self) -> Option<Ast> {
-        Some(Ast::Identifier { value: Box::new(self.cur_token.clone().literal()) })
+        Some(Ast::Identifier { value: Box::new(self.cur_token.clone().literal()), span: self.cur_token.span })
     }
     
-    fn parse_integer_literal(&self) -> Option<Ast> {
+    fn parse_integer_literal(&mut self) -> Option<Ast> {
         let value = match self.cur_token.literal().parse::<i64>() {
             Ok(value) => value,
-            Err(_)    => return None,
+            Err(_)    => {
+                self.errors.push(ParseError::InvalidInteger { found: self.cur_token.clone() });
+                return None;
+            },
         };
 
-        Some(Ast::Integer { value: value })
+        Some(Ast::Integer { value, span: self.cur_token.span })
     }
 
     fn parse_boolean_literal(&self) -> Option<Ast> {
-        Some(Ast::Boolean { value: self.cur_token_is(TokenKind::True) })
+        Some(Ast::Boolean { value: self.cur_token_is(TokenKind::True), span: self.cur_token.span })
     }
 
     fn parse_string_literal(&self) -> Option<Ast> {
-        Some(Ast::StringLiteral { value: Box::new(self.cur_token.literal()) })
+        Some(Ast::StringLiteral { value: Box::new(self.cur_token.literal()), span: self.cur_token.span })
     }
 
     fn parse_prefix_expression(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
         let operator = Box::new(self.cur_token.literal());
 
         self.next_token();
@@ -218,8 +343,9 @@ impl Parser {
         };
         
         Some(Ast::PrefixExpression {
-            operator: operator,
-            right: right,
+            operator,
+            right,
+            span,
         })
 
     }
@@ -232,6 +358,7 @@ impl Parser {
             None => return None,
         };
 
+        let span = self.cur_token.span;
         let operator = Box::new(self.cur_token.literal());
         let precedence = self.cur_precedence().clone();
         
@@ -243,9 +370,10 @@ impl Parser {
         };
 
         Some(Ast::InfixExpression {
-            left: left,
-            operator: operator,
-            right: right,
+            left,
+            operator,
+            right,
+            span,
         })
     }
 
@@ -253,6 +381,7 @@ impl Parser {
         self.next_token();
 
         let grouped_expression = self.parse_expression(Precedence::Lowest);
+        grouped_expression.as_ref()?;
 
         if !self.expect_peek(TokenKind::Rparen) {
             return None;
@@ -262,6 +391,8 @@ impl Parser {
     }
 
     fn parse_if_expression(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
+
         if !self.expect_peek(TokenKind::Lparen) {
             return None;
         }
@@ -296,32 +427,50 @@ impl Parser {
         }
 
         Some(Ast::IfExpression {
-            condition: condition,
-            body: body,
-            alternative: alternative,
+            condition,
+            body,
+            alternative,
+            span,
         })
         
     }
 
     fn parse_block_statement(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
         self.next_token();
 
         let mut statements = Vec::new();
         
-        while !self.cur_token_is(TokenKind::Rbrace) && !self.peek_token_is(TokenKind::Eof) {
+        while !self.cur_token_is(TokenKind::Rbrace) {
+            if self.cur_token_is(TokenKind::Eof) {
+                self.errors.push(ParseError::ExpectedToken {
+                    expected: TokenKind::Rbrace,
+                    found: self.cur_token.clone(),
+                });
+                return None;
+            }
+
+            let start = (self.cur_token.span, self.brackets.len());
+
             match self.parse_statement() {
                 Some(value) => statements.push(Box::new(value)),
-                None        => return None,
+                None        => {
+                    self.synchronize(start);
+                    continue;
+                },
             }
             self.next_token();
         }
         
         Some(Ast::BlockStatement {
             statements: Box::new(statements),
+            span,
         })
     }
 
     fn parse_function_literal(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
+
         if !self.expect_peek(TokenKind::Lparen) {
             return None;
         }
@@ -342,33 +491,32 @@ impl Parser {
 
         Some(Ast::FunctionLiteral {
             arguments: parameters,
-            body: body,
+            body,
+            span,
         })
     }
 
+    #[allow(clippy::vec_box)]  // Ast keeps its lists as Vec<Box<Ast>>
     fn parse_function_parameters(&mut self) -> Option<Vec<Box<Ast>>> {
         let mut parameters = Vec::new();
 
-        if self.peek_token_is(TokenKind::Lparen) {
+        if self.peek_token_is(TokenKind::Rparen) {
             self.next_token();
             return Some(parameters);
         }
 
-        self.next_token();
-        
-        match self.parse_identifier() {
-            Some(value) => parameters.push(Box::new(value)),
-            None        => return None,
+        if !self.expect_peek(TokenKind::Identifier) {
+            return None;
         }
+        parameters.push(Box::new(self.parse_identifier()?));
         
         while self.peek_token_is(TokenKind::Comma) {
             self.next_token();
-            self.next_token();
 
-            match self.parse_identifier() {
-                Some(value) => parameters.push(Box::new(value)),
-                None        => return None,
+            if !self.expect_peek(TokenKind::Identifier) {
+                return None;
             }
+            parameters.push(Box::new(self.parse_identifier()?));
         }
 
         if !self.expect_peek(TokenKind::Rparen) {
@@ -379,16 +527,60 @@ impl Parser {
     }
 
     fn parse_array_literal(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
+
         let elements = match self.parse_expression_list(TokenKind::Rbracket) {
             Some(value) => Box::new(value),
             None        => return None,
         };
 
         Some(Ast::ArrayLiteral {
-            elements: elements,
+            elements,
+            span,
         })
     }
 
+    fn parse_hash_literal(&mut self) -> Option<Ast> {
+        let span = self.cur_token.span;
+        let mut pairs = Vec::new();
+
+        while !self.peek_token_is(TokenKind::Rbrace) {
+            self.next_token();
+
+            let key = match self.parse_expression(Precedence::Lowest) {
+                Some(value) => Box::new(value),
+                None        => return None,
+            };
+
+            if !self.expect_peek(TokenKind::Colon) {
+                return None;
+            }
+
+            self.next_token();
+
+            let value = match self.parse_expression(Precedence::Lowest) {
+                Some(value) => Box::new(value),
+                None        => return None,
+            };
+
+            pairs.push((key, value));
+
+            if !self.peek_token_is(TokenKind::Rbrace) && !self.expect_peek(TokenKind::Comma) {
+                return None;
+            }
+        }
+
+        if !self.expect_peek(TokenKind::Rbrace) {
+            return None;
+        }
+
+        Some(Ast::HashLiteral {
+            pairs: Box::new(pairs),
+            span,
+        })
+    }
+
+    #[allow(clippy::vec_box)]  // Ast keeps its lists as Vec<Box<Ast>>
     fn parse_expression_list(&mut self, end: TokenKind) -> Option<Vec<Box<Ast>>> {
         if self.peek_token_is(end.clone()) {
             self.next_token();
@@ -415,7 +607,7 @@ impl Parser {
         }
 
         if !self.expect_peek(end.clone()) {
-            return Some(Vec::new());
+            return None;
         }
        
         Some(expression_list)
@@ -428,15 +620,17 @@ impl Parser {
         };
 
         self.next_token();
-        
+        let span = self.cur_token.span;
+
         let arguments = match self.parse_expression_list(TokenKind::Rparen) {
             Some(value) => Box::new(value),
             None        => return None,
         };
         
         Some(Ast::CallExpression {
-            function: function,
-            arguments: arguments,
+            function,
+            arguments,
+            span,
         })
     }
 
@@ -446,20 +640,23 @@ impl Parser {
             None        => return None,
         };
 
+        self.next_token();
+        let span = self.cur_token.span;
         self.next_token();
 
-        let index = match self.parse_expression(Precedence::Index) {
+        let index = match self.parse_expression(Precedence::Lowest) {
             Some(value) => Box::new(value),
             None        => return None,
         };
 
-        if !self.expect_peek(TokenKind::Rbrace) {
+        if !self.expect_peek(TokenKind::Rbracket) {
             return None;
         }
 
         Some(Ast::IndexExpression {
-            left: left,
-            index: index,
+            left,
+            index,
+            span,
         })
     }
     
@@ -491,30 +688,30 @@ impl Parser {
     }
 
     fn peek_error(&mut self, kind: TokenKind) {
-        let msg = format!("expected next token to be {}, got {} instead",
-                          kind.literal(), self.peek_token.kind.literal());
-        self.errors.push(msg);
+        let error = match self.peek_token.kind {
+            TokenKind::Illegal  => ParseError::IllegalToken { found: self.peek_token.clone() },
+            _                   => ParseError::ExpectedToken { expected: kind, found: self.peek_token.clone() },
+        };
+        self.errors.push(error);
     }
     
-    fn parse_error(&mut self) {
-        let msg = format!("there is no matching pattern for {} found", self.cur_token.clone().literal());
-        self.errors.push(msg);
-    }
-
-    fn no_prefix_parse_fn_error(&mut self, kind: TokenKind) {
-        let msg = format!("no prefix parse function for {} found", kind.literal());
-        self.errors.push(msg);
+    fn no_prefix_parse_fn_error(&mut self) {
+        let error = match self.cur_token.kind {
+            TokenKind::Illegal  => ParseError::IllegalToken { found: self.cur_token.clone() },
+            _                   => ParseError::NoPrefixParseFn { found: self.cur_token.clone() },
+        };
+        self.errors.push(error);
     }
     
     pub fn check_parser_errors(&self) {
-        if self.errors.len() == 0 {
+        if self.errors.is_empty() {
             return;
         }
 
         println!("parser has {} errors", self.errors.len());
         
-        for msg in self.errors.iter() {
-            println!("parser error: {}", msg);
+        for error in self.errors.iter() {
+            println!("parser error: {}", error);
         }
 
         panic!();
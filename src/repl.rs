@@ -1,37 +1,374 @@
 use crate::lexer::Lexer;
-use crate::parser:: { Parser };
+use crate::token::{ TokenKind };
+use crate::builtins::{ BUILTINS };
+use crate::cli::{ Engine, parse };
+use crate::compiler::{ Bytecode, Compiler };
+use crate::environment::{ Environment };
 use crate::eval::{ eval };
-use std::io::{ self, Write, stdin };
+use crate::object::{ Object };
+use crate::symbol_table::{ SymbolScope, SymbolTable };
+use crate::vm::{ GLOBALS_SIZE, VM };
+use rustyline::completion::{ Completer };
+use rustyline::error::{ ReadlineError };
+use rustyline::highlight::{ Highlighter };
+use rustyline::hint::{ Hinter };
+use rustyline::history::{ DefaultHistory };
+use rustyline::validate::{ Validator };
+use rustyline::{ Context, Editor, Helper };
+use std::cell::RefCell;
+use std::fs;
+use std::env;
+use std::path::{ PathBuf };
+use std::rc::Rc;
 
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+
+const HELP: &str = "\
+:tokens <source>    show the tokens the lexer produces
+:ast <source>       show the parsed program and its syntax tree
+:bytecode <source>  show the compiled constants and instructions
+:env                list the bindings of this session
+:load <file>        run a script in this session
+:quit               leave the REPL
+:help               print this message";
+
+const HISTORY_FILE: &str = ".simia_history";
+
+const KEYWORDS: &[&str] = &["else", "false", "fn", "if", "let", "return", "true"];
+const COMMANDS: &[&str] = &[":ast", ":bytecode", ":env", ":help", ":load", ":quit", ":tokens"];
+
+pub fn start(engine: Engine) {
+    let mut editor: Editor<Completion, DefaultHistory> = match Editor::new() {
+        Ok(editor)  => editor,
+        Err(error)  => {
+            eprintln!("simiaVM: cannot start the REPL: {}", error);
+            return;
+        },
+    };
+    editor.set_helper(Some(Completion { names: Vec::new() }));
+
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(ref path) = history {
+        let _ = editor.load_history(path);
+    }
+
+    let mut session = Session::new(engine);
+    let mut input = String::new();
 
-pub fn start() {
     loop {
-        print!(">> ");
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
-        
-        match stdin().read_line(&mut input) {
-            Ok(_) => {
-                let lexer = Lexer::new(input);
-                let mut parser = Parser::new(lexer);
-                let program = parser.parse_program().unwrap();
-
-                if parser.errors.len() != 0 {
-                    print_parse_errors(parser.errors);
+        let prompt = if input.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+
+        match editor.readline(prompt) {
+            Ok(line)                            => {
+                input.push_str(&line);
+                input.push('\n');
+                if is_incomplete(source_of(&input)) {
+                    continue;
+                }
+
+                let entry = std::mem::take(&mut input);
+                if entry.trim().is_empty() {
                     continue;
                 }
+                let _ = editor.add_history_entry(entry.trim_end());
 
-                let evaluated = eval(program);
-                println!("{}", evaluated.inspect());
-                
-            }
-            Err(error) => println!("error: {}", error)
+                let reply = session.handle(&entry);
+                if let Some(helper) = editor.helper_mut() {
+                    helper.names = session.bindings().into_iter().map(|(name, _)| name).collect();
+                }
+
+                match reply {
+                    Reply::Output(output)   => {
+                        if !output.is_empty() {
+                            println!("{}", output);
+                        }
+                    },
+                    Reply::Quit             => break,
+                }
+            },
+            // Ctrl-C drops whatever has been typed so far, Ctrl-D leaves.
+            Err(ReadlineError::Interrupted)     => input.clear(),
+            Err(ReadlineError::Eof)             => break,
+            Err(error)                          => {
+                eprintln!("simiaVM: {}", error);
+                break;
+            },
+        }
+    }
+
+    if let Some(ref path) = history {
+        if let Err(error) = editor.save_history(path) {
+            eprintln!("simiaVM: cannot save history to {}: {}", path.display(), error);
         }
     }
 }
 
-fn print_parse_errors(errors: Vec<String>) {
-    for error in errors {
-        println!("{}", error);
+// Candidates for the word ending at `pos`: meta-commands at the start of the
+// line, otherwise keywords, builtins and the given bound names. Returns the
+// start of the word being completed along with the sorted candidates.
+pub fn complete(line: &str, pos: usize, names: &[String]) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before.char_indices().rev()
+                      .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+                      .map_or(0, |(i, c)| i + c.len_utf8());
+
+    if before[..start].trim_start() == ":" {
+        let word = &before[start - 1..];
+        let commands = COMMANDS.iter()
+            .filter(|command| command.starts_with(word))
+            .map(|command| command.to_string())
+            .collect();
+        return (start - 1, commands);
+    }
+
+    let word = &before[start..];
+    let mut candidates: Vec<String> = KEYWORDS.iter()
+        .chain(BUILTINS.iter().map(|(name, _)| name))
+        .map(|name| name.to_string())
+        .chain(names.iter().cloned())
+        .collect();
+
+    candidates.retain(|candidate| candidate.starts_with(word));
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
+
+struct Completion {
+    names: Vec<String>,     // Bound identifiers of the session
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete(line, pos, &self.names))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+pub enum Reply {
+    Output(String),
+    Quit,
+}
+
+// Meta-commands start with ':'; only those taking source code continue
+// over several lines.
+fn source_of(input: &str) -> &str {
+    match split_command(input) {
+        Some((":tokens", source))   |
+        Some((":ast", source))      |
+        Some((":bytecode", source)) => source,
+        Some(_)                     => "",
+        None                        => input,
+    }
+}
+
+fn split_command(input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start();
+    if !input.starts_with(':') {
+        return None;
     }
+
+    match input.find(char::is_whitespace) {
+        Some(end)   => Some((&input[..end], &input[end..])),
+        None        => Some((input, "")),
+    }
+}
+
+// Bindings made by one line stay visible to the next. The VM engine keeps
+// the symbol table and constant pool across compilations and the globals
+// across runs. When a line fails at runtime the VM keeps none of the names
+// it introduced, while the evaluator keeps those bound before the error.
+pub struct Session {
+    engine: Engine,
+    env: Rc<RefCell<Environment>>,
+    symbol_table: SymbolTable,
+    constants: Vec<Object>,
+    globals: Vec<Object>,
+}
+
+impl Session {
+    pub fn new(engine: Engine) -> Session {
+        Session {
+            engine,
+            env: Rc::new(RefCell::new(Environment::new())),
+            symbol_table: Compiler::new().into_state().0,
+            constants: Vec::new(),
+            globals: vec![Object::Null; GLOBALS_SIZE],
+        }
+    }
+
+    pub fn handle(&mut self, input: &str) -> Reply {
+        let (name, argument) = match split_command(input) {
+            Some(command)   => command,
+            None            => return Reply::Output(show(self.eval(input))),
+        };
+        let argument = argument.trim();
+
+        let output = match name {
+            ":tokens"   => tokens(argument),
+            ":ast"      => match parse(argument, "<repl>") {
+                Ok(program)     => format!("{}\n{}", program.inspect(), program.tree().trim_end()),
+                Err(errors)     => errors.join("\n"),
+            },
+            ":bytecode" => match self.compile(argument) {
+                Ok(bytecode)    => bytecode.disassemble().trim_end().to_string(),
+                Err(errors)     => errors.join("\n"),
+            },
+            ":env"      => self.bindings().iter()
+                                .map(|(name, value)| format!("{} = {}", name, value.inspect()))
+                                .collect::<Vec<String>>()
+                                .join("\n"),
+            ":load"     => match fs::read_to_string(argument) {
+                Ok(source)      => show(self.run(&source, argument)),
+                Err(error)      => format!("cannot read {}: {}", argument, error),
+            },
+            ":quit"     => return Reply::Quit,
+            ":help"     => HELP.to_string(),
+            _           => format!("unknown command: {} (type :help for a list)", name),
+        };
+
+        Reply::Output(output)
+    }
+
+    pub fn eval(&mut self, input: &str) -> Result<Object, Vec<String>> {
+        self.run(input, "<repl>")
+    }
+
+    // Global bindings of the session, ordered by name.
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        match self.engine {
+            Engine::Eval    => self.env.borrow().bindings(),
+            Engine::Vm      => self.symbol_table.symbols().into_iter()
+                                   .filter(|symbol| symbol.scope == SymbolScope::Global)
+                                   .map(|symbol| (symbol.name, self.globals[symbol.index].clone()))
+                                   .collect(),
+        }
+    }
+
+    // Compiles against the session's definitions without recording the result.
+    fn compile(&self, input: &str) -> Result<Bytecode, Vec<String>> {
+        let program = parse(input, "<repl>")?;
+        let mut compiler = Compiler::new_with_state(self.symbol_table.clone(), self.constants.clone());
+        match compiler.compile(program) {
+            Ok(())      => Ok(compiler.bytecode()),
+            Err(error)  => Err(vec![format!("compile error: {}", error)]),
+        }
+    }
+
+    fn run(&mut self, input: &str, origin: &str) -> Result<Object, Vec<String>> {
+        let program = parse(input, origin)?;
+
+        match self.engine {
+            // The error's span may lie in an earlier line that defined the
+            // failing function, so it is not rendered against this one.
+            Engine::Eval    => match eval(program, &self.env) {
+                Object::Error { message, .. }   => Err(vec![format!("runtime error: {}", message)]),
+                object                      => Ok(object),
+            },
+            Engine::Vm      => {
+                // A failed compilation leaves the session as it was.
+                let mut compiler = Compiler::new_with_state(self.symbol_table.clone(), self.constants.clone());
+                if let Err(error) = compiler.compile(program) {
+                    return Err(vec![format!("compile error: {}", error)]);
+                }
+                let bytecode = compiler.bytecode();
+                let (symbol_table, constants) = compiler.into_state();
+
+                // Constants are kept even if the run fails: a closure the line
+                // stored in an existing global may refer to them.
+                self.constants = constants;
+
+                let mut vm = VM::new_with_globals(bytecode, std::mem::take(&mut self.globals));
+                let result = vm.run();
+                let object = vm.result();
+                self.globals = vm.into_globals();
+
+                match result {
+                    Ok(())      => {
+                        self.symbol_table = symbol_table;
+                        Ok(object)
+                    },
+                    Err(error)  => {
+                        // The line's new names are dropped, and their slots
+                        // cleared for whichever names take them next.
+                        let defined = self.symbol_table.num_definitions..symbol_table.num_definitions;
+                        for slot in self.globals[defined].iter_mut() {
+                            *slot = Object::Null;
+                        }
+                        Err(vec![format!("runtime error: {}", error)])
+                    },
+                }
+            },
+        }
+    }
+}
+
+fn show(result: Result<Object, Vec<String>>) -> String {
+    match result {
+        Ok(object)  => object.inspect(),
+        Err(errors) => errors.join("\n"),
+    }
+}
+
+fn tokens(input: &str) -> String {
+    let mut lexer = Lexer::new(input.to_string());
+    let mut lines = Vec::new();
+
+    loop {
+        let token = lexer.next_token();
+        if token.kind == TokenKind::Eof {
+            break;
+        }
+        lines.push(format!("{}:{} {} {:?}", token.span.line, token.span.column, token.kind.literal(), token.literal));
+    }
+
+    lines.join("\n")
+}
+
+// Input is incomplete while a bracket is left open, a string is left
+// unterminated or the last token is an operator still waiting for its operand.
+pub fn is_incomplete(input: &str) -> bool {
+    let mut lexer = Lexer::new(input.to_string());
+    let mut depth = 0;
+    let mut last = TokenKind::Eof;
+
+    loop {
+        let token = lexer.next_token();
+        match token.kind {
+            TokenKind::Eof => break,
+            TokenKind::Lparen | TokenKind::Lbrace | TokenKind::Lbracket => depth += 1,
+            TokenKind::Rparen | TokenKind::Rbrace | TokenKind::Rbracket => depth -= 1,
+            // An unterminated string runs one past the end of the input.
+            TokenKind::String if input[token.span.offset..].chars().count() < token.span.length => return true,
+            _ => {},
+        }
+        last = token.kind;
+    }
+
+    if depth > 0 {
+        return true;
+    }
+
+    matches!(last, TokenKind::Assign   |
+                   TokenKind::Plus     |
+                   TokenKind::Minus    |
+                   TokenKind::Asterisk |
+                   TokenKind::Slash    |
+                   TokenKind::Bang     |
+                   TokenKind::Eq       |
+                   TokenKind::NotEq    |
+                   TokenKind::Lt       |
+                   TokenKind::Gt)
 }
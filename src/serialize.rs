@@ -0,0 +1,245 @@
+// On-disk format for compiled programs. All multi-byte numbers are
+// big-endian, like instruction operands.
+//
+//   magic           4 bytes     "SMB\0"
+//   version         u16
+//   constant count  u32
+//   constants       tag u8 followed by the payload:
+//                     Integer           i64
+//                     String            u32 length, UTF-8 bytes
+//                     CompiledFunction  u8 parameters, u8 locals, instructions
+//   instructions    u32 length, bytes
+
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use crate::builtins::{ BUILTINS };
+use crate::code::{ Instructions, Opcode, lookup, read_operands };
+use crate::compiler::{ Bytecode };
+use crate::object::{ CompiledFunction, Object };
+
+pub const MAGIC: &[u8; 4] = b"SMB\0";
+pub const VERSION: u16 = 3;
+
+const TAG_INTEGER: u8 = 0;
+const TAG_STRING: u8 = 1;
+const TAG_COMPILED_FUNCTION: u8 = 2;
+
+impl Bytecode {
+    pub fn serialize(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_be_bytes());
+
+        write_u32(&mut bytes, self.constants.len())?;
+        for constant in self.constants.iter() {
+            write_constant(&mut bytes, constant)?;
+        }
+
+        write_instructions(&mut bytes, &self.instructions)?;
+
+        Ok(bytes)
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Bytecode, String> {
+        let mut reader = Reader { bytes, position: 0 };
+
+        if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err("not a bytecode file: bad magic header".to_string());
+        }
+
+        let version = reader.read_u16()?;
+        if version != VERSION {
+            return Err(format!("unsupported bytecode version {} (expected {})", version, VERSION));
+        }
+
+        let count = reader.read_u32()?;
+        let mut constants = Vec::new();
+        for _ in 0..count {
+            constants.push(reader.read_constant()?);
+        }
+
+        let instructions = reader.read_instructions()?;
+
+        if reader.position != bytes.len() {
+            return Err(format!("malformed bytecode: {} unexpected trailing bytes at offset {}",
+                               bytes.len() - reader.position, reader.position));
+        }
+
+        for (i, constant) in constants.iter().enumerate() {
+            if let Object::CompiledFunction { function } = constant {
+                validate_instructions(&function.instructions, &constants, &format!("constant {}", i))?;
+            }
+        }
+        validate_instructions(&instructions, &constants, "main")?;
+
+        Ok(Bytecode {
+            instructions,
+            constants,
+        })
+    }
+}
+
+// Checks what the VM relies on without checking itself on every
+// instruction: known opcodes, complete operands and operands that refer to
+// an existing constant or builtin.
+fn validate_instructions(instructions: &Instructions, constants: &[Object], location: &str) -> Result<(), String> {
+    let mut offset = 0;
+
+    while offset < instructions.len() {
+        let byte = instructions[offset];
+        let def = match Opcode::from_byte(byte).and_then(lookup) {
+            Some(value) => value,
+            None        => return Err(format!("malformed bytecode: unknown opcode {} at offset {} in {}",
+                                              byte, offset, location)),
+        };
+
+        let width: usize = def.operand_widths.iter().map(|width| *width as usize).sum();
+        if instructions.len() - offset - 1 < width {
+            return Err(format!("malformed bytecode: truncated {} at offset {} in {}", def.name, offset, location));
+        }
+
+        let (operands, read) = read_operands(&def, &instructions[offset + 1..]);
+        let problem = match def.opcode {
+            Opcode::OpConstant      if operands[0] >= constants.len() => {
+                Some(format!("refers to constant {} of {}", operands[0], constants.len()))
+            },
+            Opcode::OpClosure       => match constants.get(operands[0]) {
+                Some(Object::CompiledFunction { .. })   => None,
+                Some(constant)                          => Some(format!("refers to constant {} of type {}", operands[0], constant.kind())),
+                None                                    => Some(format!("refers to constant {} of {}", operands[0], constants.len())),
+            },
+            Opcode::OpGetBuiltin    if operands[0] >= BUILTINS.len() => {
+                Some(format!("refers to builtin {} of {}", operands[0], BUILTINS.len()))
+            },
+            _                       => None,
+        };
+
+        if let Some(problem) = problem {
+            return Err(format!("malformed bytecode: {} {} at offset {} in {}", def.name, problem, offset, location));
+        }
+
+        offset += 1 + read;
+    }
+
+    Ok(())
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) -> Result<(), String> {
+    let value = u32::try_from(value).map_err(|_| format!("value too large to serialize: {}", value))?;
+    bytes.extend_from_slice(&value.to_be_bytes());
+    Ok(())
+}
+
+fn write_u8(bytes: &mut Vec<u8>, value: usize) -> Result<(), String> {
+    let value = u8::try_from(value).map_err(|_| format!("value too large to serialize: {}", value))?;
+    bytes.push(value);
+    Ok(())
+}
+
+fn write_instructions(bytes: &mut Vec<u8>, instructions: &Instructions) -> Result<(), String> {
+    write_u32(bytes, instructions.len())?;
+    bytes.extend_from_slice(instructions);
+    Ok(())
+}
+
+fn write_constant(bytes: &mut Vec<u8>, constant: &Object) -> Result<(), String> {
+    match constant {
+        Object::Integer { value }               => {
+            bytes.push(TAG_INTEGER);
+            bytes.extend_from_slice(&value.to_be_bytes());
+        },
+        Object::String { value }                => {
+            bytes.push(TAG_STRING);
+            write_u32(bytes, value.len())?;
+            bytes.extend_from_slice(value.as_bytes());
+        },
+        Object::CompiledFunction { function }   => {
+            bytes.push(TAG_COMPILED_FUNCTION);
+            write_u8(bytes, function.num_parameters)?;
+            write_u8(bytes, function.num_locals)?;
+            write_instructions(bytes, &function.instructions)?;
+        },
+        _                                       => {
+            return Err(format!("cannot serialize constant of type {}", constant.kind()));
+        },
+    }
+
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.position < n {
+            return Err(format!("truncated bytecode: expected {} more bytes at offset {}, found {}",
+                               n, self.position, self.bytes.len() - self.position));
+        }
+
+        let slice = &self.bytes[self.position..self.position + n];
+        self.position += n;
+
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<usize, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn read_instructions(&mut self) -> Result<Instructions, String> {
+        let length = self.read_u32()?;
+        Ok(Instructions::from(self.take(length)?.to_vec()))
+    }
+
+    fn read_constant(&mut self) -> Result<Object, String> {
+        let offset = self.position;
+
+        match self.read_u8()? {
+            TAG_INTEGER             => {
+                let bytes = self.take(8)?;
+                let mut value = [0; 8];
+                value.copy_from_slice(bytes);
+
+                Ok(Object::Integer { value: i64::from_be_bytes(value) })
+            },
+            TAG_STRING              => {
+                let length = self.read_u32()?;
+                let bytes = self.take(length)?;
+
+                match String::from_utf8(bytes.to_vec()) {
+                    Ok(value)   => Ok(Object::String { value }),
+                    Err(_)      => Err(format!("malformed bytecode: invalid UTF-8 in string constant at offset {}", offset)),
+                }
+            },
+            TAG_COMPILED_FUNCTION   => {
+                let num_parameters = self.read_u8()? as usize;
+                let num_locals = self.read_u8()? as usize;
+                let instructions = self.read_instructions()?;
+
+                let function = CompiledFunction {
+                    instructions,
+                    num_locals,
+                    num_parameters,
+                };
+
+                Ok(Object::CompiledFunction { function: Rc::new(function) })
+            },
+            tag                     => Err(format!("malformed bytecode: unknown constant tag {} at offset {}", tag, offset)),
+        }
+    }
+}
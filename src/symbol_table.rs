@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum SymbolScope {
+    Global,
+    Local,
+    Builtin,
+    Free,
+    Function,     // the name a function literal is bound to, seen from inside its own body
+}
+
+#[derive(Clone,PartialEq,Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: SymbolScope,
+    pub index: usize,
+}
+
+#[derive(Clone,Default)]
+pub struct SymbolTable {
+    pub outer: Option<Box<SymbolTable>>,
+    store: HashMap<String, Symbol>,
+    pub num_definitions: usize,
+    pub free_symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable {
+            outer: None,
+            store: HashMap::new(),
+            num_definitions: 0,
+            free_symbols: Vec::new(),
+        }
+    }
+
+    pub fn new_enclosed(outer: SymbolTable) -> SymbolTable {
+        let mut table = SymbolTable::new();
+        table.outer = Some(Box::new(outer));
+        table
+    }
+
+    pub fn define(&mut self, name: &str) -> Symbol {
+        let scope = match self.outer {
+            Some(_) => SymbolScope::Local,
+            None    => SymbolScope::Global,
+        };
+
+        // Rebinding a name in the same scope reuses its slot, so closures
+        // that refer to it see the new value, as they do in `eval`.
+        if let Some(existing) = self.store.get(name) {
+            if existing.scope == scope {
+                return existing.clone();
+            }
+        }
+
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope,
+            index: self.num_definitions,
+        };
+
+        self.store.insert(name.to_string(), symbol.clone());
+        self.num_definitions += 1;
+
+        symbol
+    }
+
+    // Defines `name` in the outermost, global scope, however deeply this
+    // table is nested.
+    pub fn define_global(&mut self, name: &str) -> Symbol {
+        match self.outer {
+            Some(ref mut outer) => outer.define_global(name),
+            None                => self.define(name),
+        }
+    }
+
+    pub fn define_builtin(&mut self, index: usize, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope: SymbolScope::Builtin,
+            index,
+        };
+
+        self.store.insert(name.to_string(), symbol.clone());
+
+        symbol
+    }
+
+    pub fn define_function_name(&mut self, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope: SymbolScope::Function,
+            index: 0,
+        };
+
+        self.store.insert(name.to_string(), symbol.clone());
+
+        symbol
+    }
+
+    // Symbols of this scope only, ordered by name.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let mut symbols: Vec<Symbol> = self.store.values().cloned().collect();
+        symbols.sort_by(|a, b| a.name.cmp(&b.name));
+        symbols
+    }
+
+    fn define_free(&mut self, original: Symbol) -> Symbol {
+        let symbol = Symbol {
+            name: original.name.to_string(),
+            scope: SymbolScope::Free,
+            index: self.free_symbols.len(),
+        };
+
+        self.free_symbols.push(original);
+        self.store.insert(symbol.name.to_string(), symbol.clone());
+
+        symbol
+    }
+
+    // Resolving a local of an enclosing function turns it into a free symbol
+    // of every table in between, so each closure captures it from its parent.
+    pub fn resolve(&mut self, name: &str) -> Option<Symbol> {
+        if let Some(symbol) = self.store.get(name) {
+            return Some(symbol.clone());
+        }
+
+        let symbol = match self.outer {
+            Some(ref mut outer) => outer.resolve(name)?,
+            None                => return None,
+        };
+
+        match symbol.scope {
+            SymbolScope::Global     |
+            SymbolScope::Builtin    => Some(symbol),
+            _                       => Some(self.define_free(symbol)),
+        }
+    }
+}
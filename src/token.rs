@@ -32,6 +32,7 @@ pub enum TokenKind {
     
     // Delimeter
     Semicolon,    // ';'
+    Colon,        // ':'
     Comma,        // ','
 
     Lparen,       // '('
@@ -69,6 +70,7 @@ impl TokenKind {
             TokenKind::True       => "True".to_string(),
             TokenKind::False      => "False".to_string(),
             TokenKind::Semicolon  => "Semicolon".to_string(),
+            TokenKind::Colon      => "Colon".to_string(),
             TokenKind::Comma      => "Comma".to_string(),
             TokenKind::Lparen     => "Lparen".to_string(),
             TokenKind::Rparen     => "Rparen".to_string(),
@@ -80,10 +82,21 @@ impl TokenKind {
     }
 }
 
+// Where a token starts in the source. Lines and columns count from 1,
+// columns and length in characters; offset is in bytes.
+#[derive(PartialEq,Debug,Clone,Copy,Default)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
 #[derive(PartialEq,Debug,Clone)]
 pub struct Token {
     pub kind: TokenKind,
     pub literal: String,
+    pub span: Span,
 }
 
 impl Token {
@@ -112,6 +125,7 @@ impl Token {
             TokenKind::True       |
             TokenKind::False      |
             TokenKind::Semicolon  |
+            TokenKind::Colon      |
             TokenKind::Comma      |
             TokenKind::Lparen     |
             TokenKind::Rparen     |
@@ -0,0 +1,491 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::builtins::{ BUILTINS };
+use crate::code::{ Opcode, read_u16, read_u8 };
+use crate::compiler::{ Bytecode };
+use crate::eval::{ eval_bang_operator_expression, eval_index_expression, eval_infix_expression,
+                   eval_minus_prefix_operator_expression, is_truthy };
+use crate::object::{ CompiledFunction, Object };
+
+pub const STACK_SIZE: usize = 2048;
+pub const GLOBALS_SIZE: usize = 65536;
+pub const MAX_FRAMES: usize = 1024;
+
+struct Frame {
+    function: Rc<CompiledFunction>,
+    free: Vec<Rc<RefCell<Object>>>,
+    ip: usize,                  // Next instruction to execute in function.instructions
+    base_pointer: usize,        // Stack index of the first local; the callee sits just below it
+}
+
+impl Frame {
+    fn new(function: Rc<CompiledFunction>, free: Vec<Rc<RefCell<Object>>>, base_pointer: usize) -> Frame {
+        Frame {
+            function,
+            free,
+            ip: 0,
+            base_pointer,
+        }
+    }
+}
+
+pub struct VM {
+    constants: Vec<Object>,
+
+    stack: Vec<Object>,
+    sp: usize,      // Always points to the next free slot. Top of stack is stack[sp-1]
+
+    globals: Vec<Object>,
+
+    frames: Vec<Frame>,
+
+    result: Object, // Value of the last top-level statement
+}
+
+impl VM {
+    pub fn new(bytecode: Bytecode) -> VM {
+        VM::new_with_globals(bytecode, vec![Object::Null; GLOBALS_SIZE])
+    }
+
+    pub fn new_with_globals(bytecode: Bytecode, globals: Vec<Object>) -> VM {
+        let main_function = CompiledFunction {
+            instructions: bytecode.instructions,
+            num_locals: 0,
+            num_parameters: 0,
+        };
+        let main_frame = Frame::new(Rc::new(main_function), Vec::new(), 0);
+
+        VM {
+            constants: bytecode.constants,
+            stack: vec![Object::Null; STACK_SIZE],
+            sp: 0,
+            globals,
+            frames: vec![main_frame],
+            result: Object::Null,
+        }
+    }
+
+    pub fn into_globals(self) -> Vec<Object> {
+        self.globals
+    }
+
+    pub fn stack_top(&self) -> Option<&Object> {
+        if self.sp == 0 {
+            return None;
+        }
+        Some(&self.stack[self.sp - 1])
+    }
+
+    pub fn last_popped_stack_elem(&self) -> Object {
+        self.stack[self.sp].clone()
+    }
+
+    // What the program evaluates to, matching eval: the value of the last
+    // top-level statement, which is Null for a `let`. Unlike
+    // last_popped_stack_elem it is never a leftover from an earlier statement.
+    pub fn result(&self) -> Object {
+        self.result.clone()
+    }
+
+    pub fn run(&mut self) -> Result<(), String> {
+        loop {
+            let main = self.frames.len() == 1;
+            let frame = self.current_frame();
+            if frame.ip >= frame.function.instructions.len() {
+                // Only the main program may simply run out of instructions;
+                // the compiler ends every function with a return.
+                if main {
+                    break;
+                }
+                return Err("function ended without return".to_string());
+            }
+
+            let byte = frame.function.instructions[frame.ip];
+            frame.ip += 1;
+
+            let op = match Opcode::from_byte(byte) {
+                Some(value) => value,
+                None        => return Err(format!("unknown opcode {}", byte)),
+            };
+
+            match op {
+                Opcode::OpConstant      => {
+                    let index = self.read_u16_operand()?;
+                    let constant = self.constant(index)?.clone();
+                    self.push(constant)?;
+                },
+                Opcode::OpAdd           |
+                Opcode::OpSub           |
+                Opcode::OpMul           |
+                Opcode::OpDiv           |
+                Opcode::OpEqual         |
+                Opcode::OpNotEqual      |
+                Opcode::OpGreaterThan   |
+                Opcode::OpLessThan      => self.execute_binary_operation(op)?,
+                Opcode::OpPop           => {
+                    let value = self.pop()?;
+                    if self.frames.len() == 1 {
+                        self.result = value;
+                    }
+                },
+                Opcode::OpTrue          => self.push(Object::Boolean { value: true })?,
+                Opcode::OpFalse         => self.push(Object::Boolean { value: false })?,
+                Opcode::OpNull          => self.push(Object::Null)?,
+                Opcode::OpMinus         => {
+                    let operand = self.pop()?;
+                    let result = eval_minus_prefix_operator_expression(operand);
+                    self.push_result(result)?;
+                },
+                Opcode::OpBang          => {
+                    let operand = self.pop()?;
+                    self.push(eval_bang_operator_expression(operand))?;
+                },
+                Opcode::OpJump          => {
+                    let position = self.read_u16_operand()?;
+                    self.current_frame().ip = position;
+                },
+                Opcode::OpJumpNotTruthy => {
+                    let position = self.read_u16_operand()?;
+
+                    let condition = self.pop()?;
+                    if !is_truthy(&condition) {
+                        self.current_frame().ip = position;
+                    }
+                },
+                Opcode::OpSetGlobal     => {
+                    let index = self.read_u16_operand()?;
+                    self.globals[index] = self.pop()?;
+                    if self.frames.len() == 1 {
+                        self.result = Object::Null;
+                    }
+                },
+                Opcode::OpGetGlobal     => {
+                    let index = self.read_u16_operand()?;
+                    self.push(self.globals[index].clone())?;
+                },
+                Opcode::OpSetLocal      => {
+                    let index = self.read_u8_operand()?;
+                    let slot = self.local_slot(index)?;
+                    let value = self.pop()?;
+
+                    match &self.stack[slot] {
+                        Object::Cell { value: cell }    => *cell.borrow_mut() = value,
+                        _                               => self.stack[slot] = value,
+                    }
+                },
+                Opcode::OpGetLocal      => {
+                    let index = self.read_u8_operand()?;
+                    let slot = self.local_slot(index)?;
+
+                    let value = match &self.stack[slot] {
+                        Object::Cell { value }  => value.borrow().clone(),
+                        value                   => value.clone(),
+                    };
+                    self.push(value)?;
+                },
+                Opcode::OpArray         => {
+                    let length = self.read_u16_operand()?;
+
+                    let elements = self.pop_many(length)?;
+
+                    self.push(Object::Array { elements })?;
+                },
+                Opcode::OpHash          => {
+                    let length = self.read_u16_operand()?;
+
+                    let elements = self.pop_many(length)?;
+                    self.push(build_hash(elements)?)?;
+                },
+                Opcode::OpIndex         => {
+                    let index = self.pop()?;
+                    let left = self.pop()?;
+                    self.push_result(eval_index_expression(left, index))?;
+                },
+                Opcode::OpGetBuiltin    => {
+                    let index = self.read_u8_operand()?;
+
+                    let function = match BUILTINS.get(index) {
+                        Some((_, function)) => *function,
+                        None                => return Err(format!("builtin index {} out of range", index)),
+                    };
+                    self.push(Object::Builtin { function })?;
+                },
+                Opcode::OpCall          => {
+                    let num_arguments = self.read_u8_operand()?;
+                    self.call_function(num_arguments)?;
+                },
+                Opcode::OpReturnValue   => {
+                    let return_value = self.pop()?;
+
+                    // A top-level `return` ends the program with its value.
+                    if self.frames.len() == 1 {
+                        self.result = return_value;
+                        break;
+                    }
+
+                    let frame = self.pop_frame();
+                    self.sp = frame.base_pointer - 1;
+
+                    self.push(return_value)?;
+                },
+                Opcode::OpReturn        => {
+                    if self.frames.len() == 1 {
+                        self.result = Object::Null;
+                        break;
+                    }
+
+                    let frame = self.pop_frame();
+                    self.sp = frame.base_pointer - 1;
+
+                    self.push(Object::Null)?;
+                },
+                Opcode::OpClosure       => {
+                    let index = self.read_u16_operand()?;
+                    let num_free = self.read_u8_operand()?;
+
+                    self.push_closure(index, num_free)?;
+                },
+                Opcode::OpGetFree       => {
+                    let index = self.read_u8_operand()?;
+                    let free = match self.current_frame().free.get(index) {
+                        Some(value) => value.borrow().clone(),
+                        None        => return Err(format!("free variable index {} out of range", index)),
+                    };
+
+                    self.push(free)?;
+                },
+                Opcode::OpCaptureLocal  => {
+                    let index = self.read_u8_operand()?;
+                    let slot = self.local_slot(index)?;
+
+                    // The first capture moves the local into a cell that the
+                    // frame and every closure over it share from then on.
+                    if !matches!(self.stack[slot], Object::Cell { .. }) {
+                        let value = Rc::new(RefCell::new(self.stack[slot].clone()));
+                        self.stack[slot] = Object::Cell { value };
+                    }
+                    self.push(self.stack[slot].clone())?;
+                },
+                Opcode::OpCaptureFree   => {
+                    let index = self.read_u8_operand()?;
+                    let value = match self.current_frame().free.get(index) {
+                        Some(value) => Rc::clone(value),
+                        None        => return Err(format!("free variable index {} out of range", index)),
+                    };
+
+                    self.push(Object::Cell { value })?;
+                },
+                Opcode::OpCurrentClosure => {
+                    let frame = self.current_frame();
+                    let closure = Object::Closure {
+                        function: Rc::clone(&frame.function),
+                        free: frame.free.clone(),
+                    };
+
+                    self.push(closure)?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn current_frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn pop_frame(&mut self) -> Frame {
+        self.frames.pop().unwrap()
+    }
+
+    fn read_u16_operand(&mut self) -> Result<usize, String> {
+        let frame = self.current_frame();
+        if frame.function.instructions.len() - frame.ip < 2 {
+            return Err(format!("truncated operand at offset {}", frame.ip));
+        }
+        let operand = read_u16(&frame.function.instructions[frame.ip..]);
+        frame.ip += 2;
+        Ok(operand as usize)
+    }
+
+    fn read_u8_operand(&mut self) -> Result<usize, String> {
+        let frame = self.current_frame();
+        if frame.function.instructions.len() - frame.ip < 1 {
+            return Err(format!("truncated operand at offset {}", frame.ip));
+        }
+        let operand = read_u8(&frame.function.instructions[frame.ip..]);
+        frame.ip += 1;
+        Ok(operand as usize)
+    }
+
+    fn constant(&self, index: usize) -> Result<&Object, String> {
+        match self.constants.get(index) {
+            Some(value) => Ok(value),
+            None        => Err(format!("constant index {} out of range", index)),
+        }
+    }
+
+    fn local_slot(&mut self, index: usize) -> Result<usize, String> {
+        let slot = self.current_frame().base_pointer + index;
+        if slot >= STACK_SIZE {
+            return Err(format!("local index {} out of range", index));
+        }
+        Ok(slot)
+    }
+
+    fn push(&mut self, object: Object) -> Result<(), String> {
+        if self.sp >= STACK_SIZE {
+            return Err("stack overflow".to_string());
+        }
+
+        self.stack[self.sp] = object;
+        self.sp += 1;
+
+        Ok(())
+    }
+
+    // Runtime errors are produced as Error objects by the shared operator
+    // implementations in `eval`; the VM turns them into an Err instead.
+    fn push_result(&mut self, object: Object) -> Result<(), String> {
+        match object {
+            Object::Error { message, .. }   => Err(message),
+            _                           => self.push(object),
+        }
+    }
+
+    fn pop(&mut self) -> Result<Object, String> {
+        if self.sp == 0 {
+            return Err("stack underflow".to_string());
+        }
+
+        self.sp -= 1;
+        Ok(self.stack[self.sp].clone())
+    }
+
+    fn pop_many(&mut self, n: usize) -> Result<Vec<Object>, String> {
+        if n > self.sp {
+            return Err("stack underflow".to_string());
+        }
+
+        let objects = self.stack[self.sp - n..self.sp].to_vec();
+        self.sp -= n;
+        Ok(objects)
+    }
+
+    fn execute_binary_operation(&mut self, op: Opcode) -> Result<(), String> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+
+        if let (Object::Integer { value: lvalue }, Object::Integer { value: rvalue }) = (&left, &right) {
+            let result = match op {
+                Opcode::OpAdd           => lvalue.checked_add(*rvalue).map(|value| Object::Integer { value }),
+                Opcode::OpSub           => lvalue.checked_sub(*rvalue).map(|value| Object::Integer { value }),
+                Opcode::OpMul           => lvalue.checked_mul(*rvalue).map(|value| Object::Integer { value }),
+                Opcode::OpEqual         => Some(Object::Boolean { value: lvalue == rvalue }),
+                Opcode::OpNotEqual      => Some(Object::Boolean { value: lvalue != rvalue }),
+                Opcode::OpGreaterThan   => Some(Object::Boolean { value: lvalue > rvalue }),
+                Opcode::OpLessThan      => Some(Object::Boolean { value: lvalue < rvalue }),
+                _                       => None,
+            };
+
+            if let Some(value) = result {
+                return self.push(value);
+            }
+        }
+
+        let operator = match op {
+            Opcode::OpAdd           => "+",
+            Opcode::OpSub           => "-",
+            Opcode::OpMul           => "*",
+            Opcode::OpDiv           => "/",
+            Opcode::OpEqual         => "==",
+            Opcode::OpNotEqual      => "!=",
+            Opcode::OpLessThan      => "<",
+            _                       => ">",
+        };
+
+        self.push_result(eval_infix_expression(operator.to_string(), left, right))
+    }
+
+    fn call_function(&mut self, num_arguments: usize) -> Result<(), String> {
+        if num_arguments >= self.sp {
+            return Err("stack underflow".to_string());
+        }
+        let callee = self.stack[self.sp - 1 - num_arguments].clone();
+
+        match callee {
+            Object::Closure { function, free } => {
+                if num_arguments != function.num_parameters {
+                    return Err(format!("wrong number of arguments: want={}, got={}",
+                                       function.num_parameters, num_arguments));
+                }
+
+                if self.frames.len() >= MAX_FRAMES {
+                    return Err("stack overflow".to_string());
+                }
+
+                let base_pointer = self.sp - num_arguments;
+                if base_pointer + function.num_locals >= STACK_SIZE {
+                    return Err("stack overflow".to_string());
+                }
+
+                // Locals live directly above the arguments, which become the
+                // first locals of the new frame. Clearing the rest keeps a
+                // cell left behind by an earlier frame from being written to.
+                for slot in self.sp..base_pointer + function.num_locals {
+                    self.stack[slot] = Object::Null;
+                }
+                self.sp = base_pointer + function.num_locals;
+                self.frames.push(Frame::new(function, free, base_pointer));
+
+                Ok(())
+            },
+            Object::Builtin { function } => {
+                let arguments = self.stack[self.sp - num_arguments..self.sp].to_vec();
+                let result = function(arguments);
+                self.sp -= num_arguments + 1;
+
+                self.push_result(result)
+            },
+            _   => Err(format!("not a function: {}", callee.kind())),
+        }
+    }
+
+    fn push_closure(&mut self, index: usize, num_free: usize) -> Result<(), String> {
+        let function = match self.constant(index)? {
+            Object::CompiledFunction { function }   => Rc::clone(function),
+            constant => return Err(format!("not a function: {}", constant.kind())),
+        };
+
+        let free = self.pop_many(num_free)?.into_iter()
+            .map(|object| match object {
+                Object::Cell { value }  => value,
+                value                   => Rc::new(RefCell::new(value)),
+            })
+            .collect();
+
+        self.push(Object::Closure { function, free })
+    }
+}
+
+fn build_hash(elements: Vec<Object>) -> Result<Object, String> {
+    let mut pairs = BTreeMap::new();
+
+    for pair in elements.chunks(2) {
+        let (key, value) = match pair {
+            [key, value]    => (key, value),
+            _               => return Err("hash literal with an odd number of elements".to_string()),
+        };
+
+        let hash_key = match key.hash_key() {
+            Some(value) => value,
+            None        => return Err(format!("unusable as hash key: {}", key.kind())),
+        };
+
+        pairs.insert(hash_key, value.clone());
+    }
+
+    Ok(Object::Hash { pairs })
+}
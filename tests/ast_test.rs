@@ -1,6 +1,7 @@
-extern crate simiaVM;
+extern crate simia_vm;
 
-use simiaVM::ast::{ Ast };
+use simia_vm::ast::{ Ast };
+use simia_vm::token::{ Span };
 
 #[test]
 fn test_ast() {
@@ -8,13 +9,15 @@ fn test_ast() {
         expression: Box::new(
             Ast::Identifier {
                 value: Box::new("foo".to_string()),
+                span: Span::default(),
             },
         ),
+        span: Span::default(),
     };
 
-    if let Ast::ExpressionStatement { expression } = expr_stmt.clone() {
+    if let Ast::ExpressionStatement { expression, .. } = expr_stmt.clone() {
         assert_eq!(expr_stmt.clone().kind(), "ExpressionStatement".to_string());
-        if let Ast::Identifier { value } = *expression {
+        if let Ast::Identifier { value, .. } = *expression {
             assert_eq!(*value, "foo".to_string());
         }
         else {
@@ -32,6 +35,7 @@ fn test_ast_inspect() {
     // Identifier
     let ident = Ast::Identifier {
         value: Box::new("foo".to_string()),
+        span: Span::default(),
     };
 
     if let Ast::Identifier { .. } = ident {
@@ -41,12 +45,14 @@ fn test_ast_inspect() {
     // Expression Statement
     let expr_stmt = Ast::ExpressionStatement {
         expression: Box::new(ident.clone()),
+        span: Span::default(),
     };
 
     assert_eq!(expr_stmt.inspect(), "foo".to_string());
 
     let value = Box::new(Ast::Integer {
         value: 0,
+        span: Span::default(),
     });
     
     
@@ -54,6 +60,7 @@ fn test_ast_inspect() {
     let let_stmt = Ast::LetStatement {
         identifier: Box::new(ident.clone()),
         value: value.clone(),
+        span: Span::default(),
     };
 
     if let Ast::LetStatement { .. } = let_stmt {
@@ -63,6 +70,7 @@ fn test_ast_inspect() {
     // Return Statement
     let return_stmt = Ast::ReturnStatement {
         return_value: value.clone(),
+        span: Span::default(),
     };
 
     if let Ast::ReturnStatement { .. } = return_stmt {
@@ -72,6 +80,7 @@ fn test_ast_inspect() {
     // Boolean
     let boolean = Ast::Boolean {
         value: true,
+        span: Span::default(),
     };
 
     if let Ast::Boolean { .. } = boolean.clone() {
@@ -81,6 +90,7 @@ fn test_ast_inspect() {
     // Block Statement
     let block_stmt = Ast::BlockStatement {
         statements: Box::new(vec![Box::new(let_stmt.clone()), Box::new(return_stmt.clone())]),
+        span: Span::default(),
     };
 
     if let Ast::BlockStatement { .. } = block_stmt.clone() {
@@ -92,6 +102,7 @@ fn test_ast_inspect() {
         condition: Box::new(boolean.clone()),
         body: Box::new(block_stmt.clone()),
         alternative: Some(Box::new(block_stmt.clone())),
+        span: Span::default(),
     };
 
     if let Ast::IfExpression { .. } = if_expr {
@@ -102,9 +113,10 @@ fn test_ast_inspect() {
     // Function Literal    
     let func_lit = Ast::FunctionLiteral {
         arguments: Box::new(
-            vec![Box::new(Ast::Identifier{value: Box::new("a".to_string())}),
-                 Box::new(Ast::Identifier{value: Box::new("b".to_string())})]),
+            vec![Box::new(Ast::Identifier{value: Box::new("a".to_string()), span: Span::default()}),
+                 Box::new(Ast::Identifier{value: Box::new("b".to_string()), span: Span::default()})]),
         body: Box::new(block_stmt.clone()),
+        span: Span::default(),
     };
 
     if let Ast::FunctionLiteral { .. } = func_lit {
@@ -113,8 +125,9 @@ fn test_ast_inspect() {
 
     // Array Literal
     let array_lit = Ast::ArrayLiteral {
-        elements: Box::new(vec![Box::new(Ast::Integer{value:0}),
-                                Box::new(Ast::StringLiteral{value:Box::new("bar".to_string())})]),
+        elements: Box::new(vec![Box::new(Ast::Integer{value:0, span: Span::default()}),
+                                Box::new(Ast::StringLiteral{value:Box::new("bar".to_string()), span: Span::default()})]),
+        span: Span::default(),
     };
 
     if let Ast::ArrayLiteral { .. } = array_lit.clone() {
@@ -124,7 +137,8 @@ fn test_ast_inspect() {
     // Prefix Expression
     let pref_expr = Ast::PrefixExpression {
         operator: Box::new("!".to_string()),
-        right: Box::new(Ast::Boolean{value:true})
+        right: Box::new(Ast::Boolean{value:true, span: Span::default()}),
+        span: Span::default(),
     };
     
     if let Ast::PrefixExpression { .. } = pref_expr.clone() {
@@ -133,9 +147,10 @@ fn test_ast_inspect() {
     
     // Infix Expression
     let inf_expr = Ast::InfixExpression {
-        left: Box::new(Ast::Integer{value:1}),
+        left: Box::new(Ast::Integer{value:1, span: Span::default()}),
         operator: Box::new("+".to_string()),
-        right: Box::new(Ast::Integer{value:2})
+        right: Box::new(Ast::Integer{value:2, span: Span::default()}),
+        span: Span::default(),
     };
 
     if let Ast::InfixExpression { .. } = inf_expr.clone() {
@@ -144,9 +159,10 @@ fn test_ast_inspect() {
 
     // Call Expression
     let call_expr = Ast::CallExpression {
-        function: Box::new(Ast::Identifier{value:Box::new("add".to_string())}),
-        arguments: Box::new(vec![Box::new(Ast::Integer{value:0}),
-                                 Box::new(Ast::Integer{value:1})]),
+        function: Box::new(Ast::Identifier{value:Box::new("add".to_string()), span: Span::default()}),
+        arguments: Box::new(vec![Box::new(Ast::Integer{value:0, span: Span::default()}),
+                                 Box::new(Ast::Integer{value:1, span: Span::default()})]),
+        span: Span::default(),
     };
 
     if let Ast::CallExpression { .. } = call_expr.clone() {
@@ -156,7 +172,8 @@ fn test_ast_inspect() {
     // Index Expression
     let index_expr = Ast::IndexExpression {
         left: Box::new(array_lit.clone()),
-        index: Box::new(Ast::Integer{value:0}),
+        index: Box::new(Ast::Integer{value:0, span: Span::default()}),
+        span: Span::default(),
     };
 
     if let Ast::IndexExpression { .. } = index_expr.clone() {
@@ -0,0 +1,183 @@
+extern crate simia_vm;
+
+use std::fs;
+use std::io::Write;
+use std::process::{ Command, Output, Stdio };
+
+use simia_vm::cli::{ self, Engine, Source };
+use simia_vm::code::{ Opcode };
+
+fn simia(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_simiaVM"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_parse_args() {
+    let tests = [(vec!["script.monkey"], Engine::Eval, Source::File { path: "script.monkey".to_string() }),
+                 (vec!["--engine=vm", "-e", "1 + 2"], Engine::Vm, Source::Expression { source: "1 + 2".to_string() }),
+                 (vec!["-", "--engine=eval"], Engine::Eval, Source::Stdin),
+    ];
+
+    for test in tests.iter() {
+        assert_eq!(cli::parse_args(&args(&test.0)),
+                   Ok(cli::Command::Run { engine: test.1, source: test.2.clone() }));
+    }
+
+    assert_eq!(cli::parse_args(&args(&["-h"])), Ok(cli::Command::Help));
+    assert_eq!(cli::parse_args(&args(&["compile", "dir/a.monkey"])),
+               Ok(cli::Command::Compile { input: "dir/a.monkey".to_string(), output: "dir/a.smb".to_string() }));
+    assert_eq!(cli::parse_args(&args(&["compile", "-o", "out.smb", "a.monkey"])),
+               Ok(cli::Command::Compile { input: "a.monkey".to_string(), output: "out.smb".to_string() }));
+    assert_eq!(cli::parse_args(&args(&["disasm", "a.smb"])),
+               Ok(cli::Command::Disasm { path: "a.smb".to_string() }));
+
+    let errors = [(vec!["-e"], "option -e requires an argument"),
+                  (vec!["--engine=jit", "a"], "unknown engine: jit (expected eval or vm)"),
+                  (vec!["-x"], "unknown option: -x"),
+                  (vec!["a", "b"], "unexpected argument: b"),
+                  (vec!["compile"], "compile requires a script"),
+                  (vec!["compile", "a", "-o"], "option -o requires an argument"),
+                  (vec!["disasm"], "disasm requires a file"),
+                  (vec!["disasm", "a", "b"], "unexpected argument: b"),
+    ];
+
+    for test in errors.iter() {
+        assert_eq!(cli::parse_args(&args(&test.0)), Err(test.1.to_string()));
+    }
+}
+
+#[test]
+fn test_expression() {
+    for engine in ["--engine=eval", "--engine=vm"].iter() {
+        let output = simia(&[engine, "-e", "let a = [1, 2]; push(a, 3)"], "");
+
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "[1, 2, 3]\n");
+
+        let output = simia(&[engine, "-e", "let x = 5;"], "");
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "null\n");
+    }
+}
+
+#[test]
+fn test_file_and_stdin() {
+    let path = std::env::temp_dir().join(format!("simia_cli_test_{}.monkey", std::process::id()));
+    fs::write(&path, "let f = fn(x) { x * 2 };\nputs(f(21));\nf(1)\n").unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    for engine in ["--engine=eval", "--engine=vm"].iter() {
+        let output = simia(&[engine, &path], "");
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+
+        let output = simia(&[engine], "puts(\"piped\")");
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "piped\n");
+    }
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_exit_codes() {
+    let tests = [(vec!["-e", "let = 1"], 1, "parse error: expected next token to be Identifier, got Assign instead
+ --> <expression>:1:5
+  |
+1 | let = 1
+  |     ^
+"),
+                 (vec!["-e", "1 + true"], 1, "runtime error: type mismatch: Integer + Boolean
+ --> <expression>:1:3
+  |
+1 | 1 + true
+  |   ^
+"),
+                 (vec!["--engine=vm", "-e", "1 + true"], 1, "runtime error: type mismatch: Integer + Boolean\n"),
+                 (vec!["--engine=vm", "-e", "x"], 1, "compile error: identifier not found: x\n"),
+                 (vec!["/nonexistent/script.monkey"], 1, "simiaVM: cannot read /nonexistent/script.monkey"),
+                 (vec!["--bogus"], 2, "simiaVM: unknown option: --bogus\n"),
+    ];
+
+    for test in tests.iter() {
+        let output = simia(&test.0, "");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(test.1), "wrong exit code for {:?}", test.0);
+        assert!(stderr.starts_with(test.2), "wrong stderr for {:?}: {}", test.0, stderr);
+    }
+}
+
+#[test]
+fn test_compile_and_disasm() {
+    let dir = std::env::temp_dir();
+    let source = dir.join(format!("simia_compile_test_{}.monkey", std::process::id()));
+    let compiled = dir.join(format!("simia_compile_test_{}.smb", std::process::id()));
+    fs::write(&source, "let double = fn(x) { x * 2 };\nputs(double(21));\n").unwrap();
+
+    let source = source.to_str().unwrap().to_string();
+    let compiled = compiled.to_str().unwrap().to_string();
+
+    let output = simia(&["compile", &source, "-o", &compiled], "");
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = simia(&[&compiled], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+
+    let expected = "constants:
+  0000 Integer 2
+  0001 CompiledFunction [1 params, 1 locals]
+        0000 OpGetLocal 0
+        0002 OpConstant 0
+        0005 OpMul
+        0006 OpReturnValue
+  0002 Integer 21
+main:
+  0000 OpClosure 1 0
+  0004 OpSetGlobal 0
+  0007 OpGetBuiltin 1
+  0009 OpGetGlobal 0
+  0012 OpConstant 2
+  0015 OpCall 1
+  0017 OpCall 1
+  0019 OpPop
+";
+    for path in [&compiled, &source].iter() {
+        let output = simia(&["disasm", path], "");
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    }
+
+    // A structurally valid file whose program pops an empty stack.
+    let mut underflow = fs::read(&compiled).unwrap()[..6].to_vec();
+    underflow.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1, Opcode::OpPop as u8]);
+    fs::write(&compiled, &underflow).unwrap();
+    let output = simia(&[&compiled], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "runtime error: stack underflow\n");
+
+    let output = simia(&["compile", &source, "-o", &compiled], "");
+    assert_eq!(output.status.code(), Some(0));
+    fs::write(&compiled, &fs::read(&compiled).unwrap()[..10]).unwrap();
+    let output = simia(&["disasm", &compiled], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("truncated bytecode"));
+
+    fs::remove_file(&source).unwrap();
+    fs::remove_file(&compiled).unwrap();
+}
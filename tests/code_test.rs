@@ -1,12 +1,27 @@
-extern crate simiaVM;
+extern crate simia_vm;
 
-use simiaVM::code::*;
+use simia_vm::code::*;
 
 #[test]
 fn test_make() {
     let tests = [(Opcode::OpConstant,
                   vec![65534],
                   vec![Opcode::OpConstant as u8, 255, 254]),
+                 (Opcode::OpAdd,
+                  vec![],
+                  vec![Opcode::OpAdd as u8]),
+                 (Opcode::OpGetLocal,
+                  vec![255],
+                  vec![Opcode::OpGetLocal as u8, 255]),
+                 (Opcode::OpJumpNotTruthy,
+                  vec![258],
+                  vec![Opcode::OpJumpNotTruthy as u8, 1, 2]),
+                 (Opcode::OpCall,
+                  vec![3],
+                  vec![Opcode::OpCall as u8, 3]),
+                 (Opcode::OpClosure,
+                  vec![65534, 255],
+                  vec![Opcode::OpClosure as u8, 255, 254, 255]),
     ];
 
     for test in tests.iter() {
@@ -24,3 +39,102 @@ fn test_make() {
         }
     }
 }
+
+#[test]
+#[should_panic(expected = "operand 256 of OpGetLocal does not fit in 1 byte")]
+fn test_make_rejects_wide_u8_operand() {
+    make(Opcode::OpGetLocal, vec![256]);
+}
+
+#[test]
+#[should_panic(expected = "operand 65536 of OpConstant does not fit in 2 bytes")]
+fn test_make_rejects_wide_u16_operand() {
+    make(Opcode::OpConstant, vec![65536]);
+}
+
+#[test]
+fn test_lookup() {
+    let tests = [(Opcode::OpConstant, "OpConstant", vec![2]),
+                 (Opcode::OpPop, "OpPop", vec![]),
+                 (Opcode::OpSetGlobal, "OpSetGlobal", vec![2]),
+                 (Opcode::OpSetLocal, "OpSetLocal", vec![1]),
+                 (Opcode::OpHash, "OpHash", vec![2]),
+                 (Opcode::OpReturn, "OpReturn", vec![]),
+    ];
+
+    for test in tests.iter() {
+        let def = lookup(test.0).unwrap();
+        assert!(def.opcode == test.0);
+        assert_eq!(def.name, test.1);
+        assert_eq!(def.operand_widths, test.2);
+    }
+}
+
+#[test]
+fn test_instructions_string() {
+    let instructions = [make(Opcode::OpAdd, vec![]),
+                        make(Opcode::OpGetLocal, vec![1]),
+                        make(Opcode::OpConstant, vec![2]),
+                        make(Opcode::OpConstant, vec![65535]),
+                        make(Opcode::OpJump, vec![0]),
+                        make(Opcode::OpClosure, vec![65535, 255]),
+    ];
+
+    let expected = "0000 OpAdd
+0001 OpGetLocal 1
+0003 OpConstant 2
+0006 OpConstant 65535
+0009 OpJump 0
+0012 OpClosure 65535 255
+";
+
+    let concatted = Instructions::from(instructions.concat());
+
+    assert_eq!(concatted.to_string(), expected);
+}
+
+#[test]
+fn test_instructions_string_malformed() {
+    let mut bytes = make(Opcode::OpPop, vec![]);
+    bytes.push(255);
+    bytes.append(&mut make(Opcode::OpConstant, vec![1])[..2].to_vec());
+
+    let expected = "0000 OpPop
+ERROR: unknown opcode 255
+0002 ERROR: truncated OpConstant
+";
+
+    assert_eq!(Instructions::from(bytes).to_string(), expected);
+}
+
+#[test]
+fn test_read_operands() {
+    let tests = [(Opcode::OpConstant, vec![65535], 2),
+                 (Opcode::OpGetLocal, vec![255], 1),
+                 (Opcode::OpAdd, vec![], 0),
+                 (Opcode::OpClosure, vec![65535, 255], 3),
+    ];
+
+    for test in tests.iter() {
+        let instruction = make(test.0, test.1.clone());
+        let def = lookup(test.0).unwrap();
+
+        let (operands_read, n) = read_operands(&def, &instruction[1..]);
+        assert_eq!(n, test.2);
+
+        for (i, want) in test.1.iter().enumerate() {
+            assert_eq!(operands_read[i], *want);
+        }
+    }
+}
+
+#[test]
+fn test_opcode_from_byte() {
+    for byte in 0..=255u8 {
+        if let Some(op) = Opcode::from_byte(byte) {
+            assert_eq!(op as u8, byte);
+        }
+    }
+
+    assert!(Opcode::from_byte(255).is_none());
+}
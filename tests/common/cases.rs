@@ -0,0 +1,250 @@
+// Programs with the kind and inspect() of what they evaluate to. eval_test
+// checks them against the tree-walker and differential_test runs every one
+// through both engines.
+
+pub type Case = (&'static str, &'static str, &'static str);
+
+pub const INTEGER_EXPRESSIONS: &[Case] = &[
+    ("5", "Integer", "5"),
+    ("10", "Integer", "10"),
+    ("-5", "Integer", "-5"),
+    ("-10", "Integer", "-10"),
+    ("5 + 5 + 5 + 5 - 10", "Integer", "10"),
+    ("2 * 2 * 2 * 2 * 2", "Integer", "32"),
+    ("-50 + 100 +  -50", "Integer", "0"),
+    ("5 * 2 + 10", "Integer", "20"),
+    ("5 + 2 * 10", "Integer", "25"),
+    ("20 + 2 * -10", "Integer", "0"),
+    ("50 / 2 * 2 + 10", "Integer", "60"),
+    ("2 * (5 + 10)", "Integer", "30"),
+    ("3 * 3 * 3 + 10", "Integer", "37"),
+    ("3 * (3 * 3) + 10", "Integer", "37"),
+    ("(5 + 10 * 2 + 15 / 3) * 2 + -10", "Integer", "50"),
+];
+
+pub const BOOLEAN_EXPRESSIONS: &[Case] = &[
+    ("true", "Boolean", "true"),
+    ("false", "Boolean", "false"),
+    ("1 < 2", "Boolean", "true"),
+    ("1 > 2", "Boolean", "false"),
+    ("1 < 1", "Boolean", "false"),
+    ("1 > 1", "Boolean", "false"),
+    ("1 == 1", "Boolean", "true"),
+    ("1 != 1", "Boolean", "false"),
+    ("1 == 2", "Boolean", "false"),
+    ("1 != 2", "Boolean", "true"),
+    ("true == true", "Boolean", "true"),
+    ("false == false", "Boolean", "true"),
+    ("true == false", "Boolean", "false"),
+    ("true != false", "Boolean", "true"),
+    ("false != true", "Boolean", "true"),
+    ("(1 < 2) == true", "Boolean", "true"),
+    ("(1 < 2) == false", "Boolean", "false"),
+    ("(1 > 2) == true", "Boolean", "false"),
+    ("(1 > 2) == false", "Boolean", "true"),
+];
+
+pub const BANG_OPERATOR: &[Case] = &[
+    ("!true", "Boolean", "false"),
+    ("!false", "Boolean", "true"),
+    ("!5", "Boolean", "false"),
+    ("!!true", "Boolean", "true"),
+    ("!!false", "Boolean", "false"),
+    ("!!5", "Boolean", "true"),
+];
+
+pub const LET_STATEMENTS: &[Case] = &[
+    ("let a = 5; a;", "Integer", "5"),
+    ("let a = 5 * 5; a;", "Integer", "25"),
+    ("let a = 5; let b = a; b;", "Integer", "5"),
+    ("let a = 5; let b = a; let c = a + b + 5; c;", "Integer", "15"),
+    ("let x = 5; x * 2", "Integer", "10"),
+    ("let x = 5;", "Null", "null"),
+    ("5; let x = 6;", "Null", "null"),
+    ("let f = fn(x) { x }; let y = f(1);", "Null", "null"),
+    ("let x = 1; if (true) { let x = 2; }", "Null", "null"),
+    ("let f = fn() { let x = 1; }; f()", "Null", "null"),
+];
+
+pub const IDENTIFIER_NOT_FOUND: &[Case] = &[
+    ("foobar", "Error", "ERROR: identifier not found: foobar"),
+    ("let a = 5; b * a", "Error", "ERROR: identifier not found: b"),
+    ("-foobar; 5", "Error", "ERROR: identifier not found: foobar"),
+    ("let a = b; a", "Error", "ERROR: identifier not found: b"),
+];
+
+pub const FUNCTION_APPLICATION: &[Case] = &[
+    ("fn(x) { x + 2; };", "Function", "fn(x){ (x + 2) }"),
+    ("let identity = fn(x) { x; }; identity(5);", "Integer", "5"),
+    ("let double = fn(x) { x * 2; }; double(5);", "Integer", "10"),
+    ("let add = fn(x, y) { x + y; }; add(5, 5);", "Integer", "10"),
+    ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));", "Integer", "20"),
+    ("let five = fn() { 5; }; five();", "Integer", "5"),
+    ("fn(x) { x; }(5)", "Integer", "5"),
+    ("let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) } }; f(500)", "Integer", "0"),
+    ("let a = fn() { b() }; let b = fn() { 1 }; a()", "Integer", "1"),
+    ("let a = fn() { fn() { c } }; let c = 5; a()()", "Integer", "5"),
+    ("let isEven = fn(n) { if (n == 0) { true } else { isOdd(n - 1) } }; let isOdd = fn(n) { if (n == 0) { false } else { isEven(n - 1) } }; isEven(10)", "Boolean", "true"),
+    ("let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(600)", "Integer", "600"),
+];
+
+pub const CLOSURES: &[Case] = &[
+    ("let newAdder = fn(x) { fn(y) { x + y }; }; let addTwo = newAdder(2); addTwo(2);", "Integer", "4"),
+    ("let adder = fn(x) { fn(y) { x + y } }; adder(2)(3)", "Integer", "5"),
+    ("let x = 10; let f = fn(y) { x + y }; let x = 20; f(1)", "Integer", "21"),
+    ("let outer = fn(a) { fn(b) { fn(c) { a + b + c } } }; outer(1)(2)(3)", "Integer", "6"),
+    ("fn() { let x = 1; let g = fn() { x }; let x = 2; g() }()", "Integer", "2"),
+    ("fn() { let x = 1; let g = fn() { fn() { x } }; let x = 3; g()() }()", "Integer", "3"),
+    ("let f = fn(a) { let g = fn() { a }; let a = a + 10; g() }; f(1)", "Integer", "11"),
+    ("let make = fn(n) { let g = fn() { n }; g }; make(1)() + make(2)()", "Integer", "3"),
+    ("let wrap = fn(x) { let y = x * 2; fn() { let z = y + 1; fn() { x + y + z } } }; wrap(1)()()", "Integer", "6"),
+    ("let counter = fn(x) { fn() { x + 1 } }; let f = counter(1); let g = counter(10); f() + g()", "Integer", "13"),
+    ("let compose = fn(f, g) { fn(x) { g(f(x)) } }; compose(fn(x) { x + 1 }, fn(x) { x * 2 })(3)", "Integer", "8"),
+    ("let f = fn(a) { let g = fn() { a }; fn() { g() + 1 } }; f(4)()", "Integer", "5"),
+    ("let wrapper = fn() { let count = fn(x) { if (x == 0) { 0 } else { 1 + count(x - 1) } }; count(3) }; wrapper()",
+     "Integer", "3"),
+    ("let f = fn(x) { fn(y) { let x = y; x } }; f(1)(2)", "Integer", "2"),
+    ("let make = fn(x) { let inner = fn() { x }; }; make(1)", "Null", "null"),
+];
+
+pub const IF_ELSE_EXPRESSIONS: &[Case] = &[
+    ("if (true) { 10 }", "Integer", "10"),
+    ("if (false) { 10 }", "Null", "null"),
+    ("if (1) { 10 }", "Integer", "10"),
+    ("if (1 < 2) { 10 }", "Integer", "10"),
+    ("if (1 > 2) { 10 }", "Null", "null"),
+    ("if (1 > 2) { 10 } else { 20 }", "Integer", "20"),
+    ("if (1 < 2) { 10 } else { 20 }", "Integer", "10"),
+];
+
+pub const RETURN_STATEMENTS: &[Case] = &[
+    ("return 10;", "Integer", "10"),
+    ("return 10; 9;", "Integer", "10"),
+    ("return 2 * 5; 9;", "Integer", "10"),
+    ("9; return 2 * 5; 9;", "Integer", "10"),
+    ("if (10 > 1) { if (10 > 1) { return 10; } return 1; }", "Integer", "10"),
+    ("if (10 > 1) { if (10 > 1) { if (true) { return 10; } } return 1; } 2", "Integer", "10"),
+    ("let f = fn(x) { return x; x + 10; }; f(10);", "Integer", "10"),
+    ("let f = fn(x) { let result = x + 10; return result; return 10; }; f(10);", "Integer", "20"),
+    ("let inner = fn() { return 1; }; let outer = fn() { inner(); return 2; }; outer();", "Integer", "2"),
+    ("let f = fn(x) { if (x > 1) { return x; } 0 }; f(5) + f(1);", "Integer", "5"),
+    ("let x = 1; return x; let y = 2;", "Integer", "1"),
+    ("let f = fn(n) { let y = if (n < 0) { return 0 - 1; } else { n * 2 }; y + 100 }; f(0 - 5)", "Integer", "-1"),
+    ("let f = fn(n) { let y = if (n < 0) { return 0 - 1; } else { n * 2 }; y + 100 }; f(5)", "Integer", "110"),
+    ("[1, if (true) { return 2; }]", "Integer", "2"),
+    ("let f = fn(n) { puts(if (n < 0) { return 7; } else { 1 }); 99 }; f(0 - 1)", "Integer", "7"),
+    ("let f = fn(n) { -if (n < 0) { return 7; } else { 1 } }; f(0 - 1)", "Integer", "7"),
+    ("let f = fn(n) { 1 + if (n < 0) { return 7; } else { 1 } }; f(0 - 1)", "Integer", "7"),
+    ("let f = fn(n) { {\"a\": if (n < 0) { return 7; } else { 1 }} }; f(0 - 1)", "Integer", "7"),
+    ("let f = fn(n) { [1, 2][if (n < 0) { return 7; } else { 1 }] }; f(0 - 1)", "Integer", "7"),
+    ("let f = fn(n) { if (if (n < 0) { return 7; } else { true }) { 1 } }; f(0 - 1)", "Integer", "7"),
+];
+
+pub const ERRORS: &[Case] = &[
+    ("5 + true;", "Error", "ERROR: type mismatch: Integer + Boolean"),
+    ("5 + true; 5;", "Error", "ERROR: type mismatch: Integer + Boolean"),
+    ("-true", "Error", "ERROR: unknown operator: -Boolean"),
+    ("1 < true", "Error", "ERROR: type mismatch: Integer < Boolean"),
+    ("(1 + true) < (\"a\" - \"b\")", "Error", "ERROR: type mismatch: Integer + Boolean"),
+    ("true + false;", "Error", "ERROR: unknown operator: Boolean + Boolean"),
+    ("5; true + false; 5", "Error", "ERROR: unknown operator: Boolean + Boolean"),
+    ("if (10 > 1) { true + false; }", "Error", "ERROR: unknown operator: Boolean + Boolean"),
+    ("if (10 > 1) { if (10 > 1) { return true + false; } return 1; }", "Error", "ERROR: unknown operator: Boolean + Boolean"),
+    ("if (true + 1) { 10 }", "Error", "ERROR: type mismatch: Boolean + Integer"),
+    ("let f = fn(x) { x / 0 }; f(10); 1", "Error", "ERROR: division by zero: 10 / 0"),
+    ("let f = fn(x) { x }; f(1 > true)", "Error", "ERROR: type mismatch: Integer > Boolean"),
+    ("let f = fn(x) { x }; f(1, 2)", "Error", "ERROR: wrong number of arguments: want=1, got=2"),
+    ("let x = 5; x(1)", "Error", "ERROR: not a function: Integer"),
+    ("9223372036854775807 + 1", "Error", "ERROR: integer overflow: 9223372036854775807 + 1"),
+    ("\"Hello\" - \"World\"", "Error", "ERROR: unknown operator: String - String"),
+    ("\"Hello\" + 1", "Error", "ERROR: type mismatch: String + Integer"),
+    ("let f = fn(n) { f(n + 1) }; f(0)", "Error", "ERROR: stack overflow"),
+    ("let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(5000)", "Error", "ERROR: stack overflow"),
+];
+
+pub const STRINGS: &[Case] = &[
+    ("\"Hello World!\"", "String", "Hello World!"),
+    ("\"tab\\tand\\nnewline\"", "String", "tab\tand\nnewline"),
+    ("\"say \\\"hi\\\"\"", "String", "say \"hi\""),
+    ("\"back\\\\slash\"", "String", "back\\slash"),
+    ("\"héllo wörld\"", "String", "héllo wörld"),
+    ("\"Hello\" + \" \" + \"World!\"", "String", "Hello World!"),
+    ("let greet = fn(name) { \"Hello, \" + name }; greet(\"monkey\")", "String", "Hello, monkey"),
+    ("\"a\" == \"a\"", "Boolean", "true"),
+    ("\"a\" == \"b\"", "Boolean", "false"),
+    ("\"a\" != \"b\"", "Boolean", "true"),
+    ("\"a\" != \"a\"", "Boolean", "false"),
+    ("\"a\" + \"b\" == \"ab\"", "Boolean", "true"),
+];
+
+pub const ARRAYS: &[Case] = &[
+    ("[1, 2 * 2, 3 + 3]", "Array", "[1, 4, 6]"),
+    ("[1, \"two\", [true]]", "Array", "[1, two, [true]]"),
+    ("[]", "Array", "[]"),
+    ("[1, 2, 3][0]", "Integer", "1"),
+    ("[1, 2, 3][1]", "Integer", "2"),
+    ("[1, 2, 3][2]", "Integer", "3"),
+    ("let i = 0; [1][i];", "Integer", "1"),
+    ("[1, 2, 3][1 + 1];", "Integer", "3"),
+    ("let myArray = [1, 2, 3]; myArray[2];", "Integer", "3"),
+    ("let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];", "Integer", "6"),
+    ("let myArray = [1, 2, 3]; let i = myArray[0]; myArray[i]", "Integer", "2"),
+    ("[1, 2, 3][-1]", "Integer", "3"),
+    ("[1, 2, 3][-3]", "Integer", "1"),
+    ("[[1, 2], [3, 4]][1][0]", "Integer", "3"),
+    ("[1, 2, 3][3]", "Error", "ERROR: index out of bounds: index=3, length=3"),
+    ("[1, 2, 3][-4]", "Error", "ERROR: index out of bounds: index=-4, length=3"),
+    ("[][0]", "Error", "ERROR: index out of bounds: index=0, length=0"),
+    ("[1, 2, 3][true]", "Error", "ERROR: index operator not supported: Array[Boolean]"),
+    ("1[0]", "Error", "ERROR: index operator not supported: Integer[Integer]"),
+    ("[1, x][0]", "Error", "ERROR: identifier not found: x"),
+];
+
+pub const HASHES: &[Case] = &[
+    ("let two = \"two\"; {\"one\": 10 - 9, two: 1 + 1, \"thr\" + \"ee\": 6 / 2, 4: 4, true: 5, false: 6}",
+     "Hash", "{4: 4, false: 6, true: 5, one: 1, three: 3, two: 2}"),
+    ("{\"b\": 2, \"a\": [1]}", "Hash", "{a: [1], b: 2}"),
+    ("{\"foo\": 5}[\"foo\"]", "Integer", "5"),
+    ("{\"foo\": 5}[\"bar\"]", "Null", "null"),
+    ("let key = \"foo\"; {\"foo\": 5}[key]", "Integer", "5"),
+    ("{}[\"foo\"]", "Null", "null"),
+    ("{5: 5}[5]", "Integer", "5"),
+    ("{true: 5}[true]", "Integer", "5"),
+    ("{false: 5}[false]", "Integer", "5"),
+    ("let config = {\"retries\": 3}; config[\"retries\"] * 2", "Integer", "6"),
+    ("{\"name\": \"Monkey\"}[fn(x) { x }];", "Error", "ERROR: unusable as hash key: Function"),
+    ("{[1]: 2}", "Error", "ERROR: unusable as hash key: Array"),
+    ("{\"a\": x}", "Error", "ERROR: identifier not found: x"),
+];
+
+pub const BUILTIN_FUNCTIONS: &[Case] = &[
+    ("len(\"\")", "Integer", "0"),
+    ("len(\"four\")", "Integer", "4"),
+    ("len(\"hello world\")", "Integer", "11"),
+    ("len(\"héllo\")", "Integer", "5"),
+    ("len([1, 2, 3])", "Integer", "3"),
+    ("len({1: 2})", "Integer", "1"),
+    ("first([1, 2, 3])", "Integer", "1"),
+    ("first([])", "Null", "null"),
+    ("last([1, 2, 3])", "Integer", "3"),
+    ("last([])", "Null", "null"),
+    ("rest([1, 2, 3])", "Array", "[2, 3]"),
+    ("rest([])", "Null", "null"),
+    ("push([], 1)", "Array", "[1]"),
+    ("let a = [1]; push(a, 2); a", "Array", "[1]"),
+    ("puts(\"hello\", \"world!\")", "Null", "null"),
+    ("let len = fn(x) { 42 }; len([1])", "Integer", "42"),
+    ("len(1)", "Error", "ERROR: argument to `len` not supported, got Integer"),
+    ("len(\"one\", \"two\")", "Error", "ERROR: wrong number of arguments: want=1, got=2"),
+    ("first(1)", "Error", "ERROR: argument to `first` must be Array, got Integer"),
+    ("last(1)", "Error", "ERROR: argument to `last` must be Array, got Integer"),
+    ("rest(\"abc\")", "Error", "ERROR: argument to `rest` must be Array, got String"),
+    ("push(1, 1)", "Error", "ERROR: argument to `push` must be Array, got Integer"),
+    ("push([1])", "Error", "ERROR: wrong number of arguments: want=2, got=1"),
+];
+
+pub const ALL: &[&[Case]] = &[
+    INTEGER_EXPRESSIONS, BOOLEAN_EXPRESSIONS, BANG_OPERATOR, LET_STATEMENTS, IDENTIFIER_NOT_FOUND,
+    FUNCTION_APPLICATION, CLOSURES, IF_ELSE_EXPRESSIONS, RETURN_STATEMENTS, ERRORS, STRINGS,
+    ARRAYS, HASHES, BUILTIN_FUNCTIONS,
+];
@@ -0,0 +1,106 @@
+// Shared by the integration tests that run source through both engines.
+// Each test crate uses only part of it.
+#![allow(dead_code)]
+
+pub mod cases;
+
+use std::cell::RefCell;
+use std::panic;
+use std::rc::Rc;
+use std::thread;
+
+use simia_vm::compiler::{ Compiler };
+use simia_vm::environment::{ Environment };
+use simia_vm::eval::{ eval };
+use simia_vm::lexer::{ Lexer };
+use simia_vm::object::{ Object };
+use simia_vm::parser::{ Parser };
+use simia_vm::vm::{ VM };
+
+// What a program evaluated to, reduced to the parts both engines are
+// expected to agree on.
+#[derive(Debug,PartialEq)]
+pub enum Outcome {
+    Value { kind: String, inspect: String },
+    Error { message: String },
+}
+
+impl Outcome {
+    fn from_object(object: Object) -> Outcome {
+        match object {
+            Object::Error { message, .. }   => Outcome::from_error(&message),
+            // The tree-walker yields Function objects and the VM yields
+            // Closures; their bodies are printed differently, so only the
+            // fact that a function came back is compared.
+            Object::Function { .. }         |
+            Object::CompiledFunction { .. } |
+            Object::Closure { .. }          => Outcome::Value {
+                kind: "Function".to_string(),
+                inspect: "<function>".to_string(),
+            },
+            _                               => Outcome::Value {
+                kind: object.kind(),
+                inspect: object.inspect(),
+            },
+        }
+    }
+
+    fn from_error(message: &str) -> Outcome {
+        Outcome::Error { message: message.to_string() }
+    }
+}
+
+fn parse(input: &str) -> simia_vm::ast::Ast {
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    parser.check_parser_errors();
+
+    program.unwrap()
+}
+
+// `eval` recurses on the native stack, and reaching the call depth limit
+// takes more of it than a test thread has, just as in the binary.
+const EVAL_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+pub fn on_large_stack<F, T>(f: F) -> T
+    where F: FnOnce() -> T + Send + 'static, T: Send + 'static
+{
+    thread::Builder::new()
+        .stack_size(EVAL_STACK_SIZE)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+pub fn run_eval(input: &str) -> Outcome {
+    let input = input.to_string();
+
+    on_large_stack(move || {
+        let env = Rc::new(RefCell::new(Environment::new()));
+
+        Outcome::from_object(eval(parse(&input), &env))
+    })
+}
+
+pub fn run_vm(input: &str) -> Outcome {
+    let mut compiler = Compiler::new();
+    if let Err(error) = compiler.compile(parse(input)) {
+        return Outcome::from_error(&error);
+    }
+
+    let mut vm = VM::new(compiler.bytecode());
+    match vm.run() {
+        Ok(())      => Outcome::from_object(vm.result()),
+        Err(error)  => Outcome::from_error(&error),
+    }
+}
+
+pub fn assert_engines_agree(input: &str) {
+    let evaluated = run_eval(input);
+    let executed = run_vm(input);
+
+    assert_eq!(evaluated, executed, "engines disagree on:\n{}", input);
+}
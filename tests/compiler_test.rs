@@ -0,0 +1,520 @@
+extern crate simia_vm;
+
+use simia_vm::ast::{ Ast };
+use simia_vm::code::{ Instructions, Opcode, make };
+use simia_vm::compiler::{ Compiler };
+use simia_vm::lexer::{ Lexer };
+use simia_vm::object::{ Object };
+use simia_vm::parser::{ Parser };
+
+#[derive(Clone)]
+enum Constant {
+    Integer(i64),
+    String(&'static str),
+    Instructions(Vec<Vec<u8>>),
+}
+
+type CompilerTestCase = (&'static str, Vec<Constant>, Vec<Vec<u8>>);
+
+fn parse(input: &str) -> Ast {
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    parser.check_parser_errors();
+
+    program.unwrap()
+}
+
+fn test_instructions(expected: Vec<Vec<u8>>, actual: &Instructions) {
+    let concatted = Instructions::from(expected.concat());
+
+    assert_eq!(actual.to_string(), concatted.to_string(), "wrong instructions");
+    assert_eq!(actual.len(), concatted.len());
+}
+
+fn test_constants(expected: Vec<Constant>, actual: &[Object]) {
+    assert_eq!(actual.len(), expected.len(), "wrong number of constants");
+
+    for (i, constant) in expected.iter().enumerate() {
+        match (constant, &actual[i]) {
+            (Constant::Integer(want), Object::Integer { value }) => assert_eq!(value, want),
+            (Constant::String(want), Object::String { value })   => assert_eq!(value, want),
+            (Constant::Instructions(want), Object::CompiledFunction { function }) => {
+                test_instructions(want.clone(), &function.instructions);
+            },
+            (_, object) => panic!("constant {} has wrong type. got={}", i, object.kind()),
+        }
+    }
+}
+
+fn run_compiler_tests(tests: Vec<CompilerTestCase>) {
+    for test in tests {
+        let program = parse(test.0);
+        let mut compiler = Compiler::new();
+
+        if let Err(error) = compiler.compile(program) {
+            panic!("compiler error: {}", error);
+        }
+
+        let bytecode = compiler.bytecode();
+
+        test_instructions(test.2, &bytecode.instructions);
+        test_constants(test.1, &bytecode.constants);
+    }
+}
+
+#[test]
+fn test_integer_arithmetic() {
+    let tests = vec![
+        ("1 + 2",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpAdd, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("1; 2",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpPop, vec![]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpPop, vec![])]),
+        ("1 - 2",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpSub, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("1 * 2",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpMul, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("2 / 1",
+         vec![Constant::Integer(2), Constant::Integer(1)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpDiv, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("-1",
+         vec![Constant::Integer(1)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpMinus, vec![]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_boolean_expressions() {
+    let tests = vec![
+        ("true",
+         vec![],
+         vec![make(Opcode::OpTrue, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("false",
+         vec![],
+         vec![make(Opcode::OpFalse, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("1 > 2",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpGreaterThan, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("1 < 2",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpLessThan, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("1 == 2",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpEqual, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("1 != 2",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpNotEqual, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("true != false",
+         vec![],
+         vec![make(Opcode::OpTrue, vec![]),
+              make(Opcode::OpFalse, vec![]),
+              make(Opcode::OpNotEqual, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("!true",
+         vec![],
+         vec![make(Opcode::OpTrue, vec![]),
+              make(Opcode::OpBang, vec![]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_conditionals() {
+    let tests = vec![
+        ("if (true) { 10 }; 3333;",
+         vec![Constant::Integer(10), Constant::Integer(3333)],
+         vec![make(Opcode::OpTrue, vec![]),                 // 0000
+              make(Opcode::OpJumpNotTruthy, vec![10]),      // 0001
+              make(Opcode::OpConstant, vec![0]),            // 0004
+              make(Opcode::OpJump, vec![11]),               // 0007
+              make(Opcode::OpNull, vec![]),                 // 0010
+              make(Opcode::OpPop, vec![]),                  // 0011
+              make(Opcode::OpConstant, vec![1]),            // 0012
+              make(Opcode::OpPop, vec![])]),                // 0015
+        ("if (true) { 10 } else { 20 }; 3333;",
+         vec![Constant::Integer(10), Constant::Integer(20), Constant::Integer(3333)],
+         vec![make(Opcode::OpTrue, vec![]),                 // 0000
+              make(Opcode::OpJumpNotTruthy, vec![10]),      // 0001
+              make(Opcode::OpConstant, vec![0]),            // 0004
+              make(Opcode::OpJump, vec![13]),               // 0007
+              make(Opcode::OpConstant, vec![1]),            // 0010
+              make(Opcode::OpPop, vec![]),                  // 0013
+              make(Opcode::OpConstant, vec![2]),            // 0014
+              make(Opcode::OpPop, vec![])]),                // 0017
+        ("if (true) { }",
+         vec![],
+         vec![make(Opcode::OpTrue, vec![]),                 // 0000
+              make(Opcode::OpJumpNotTruthy, vec![8]),       // 0001
+              make(Opcode::OpNull, vec![]),                 // 0004
+              make(Opcode::OpJump, vec![9]),                // 0005
+              make(Opcode::OpNull, vec![]),                 // 0008
+              make(Opcode::OpPop, vec![])]),                // 0009
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_global_let_statements() {
+    let tests = vec![
+        ("let one = 1; let two = 2;",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpSetGlobal, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpSetGlobal, vec![1])]),
+        ("let one = 1; one;",
+         vec![Constant::Integer(1)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpSetGlobal, vec![0]),
+              make(Opcode::OpGetGlobal, vec![0]),
+              make(Opcode::OpPop, vec![])]),
+        ("let one = 1; let two = one; two;",
+         vec![Constant::Integer(1)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpSetGlobal, vec![0]),
+              make(Opcode::OpGetGlobal, vec![0]),
+              make(Opcode::OpSetGlobal, vec![1]),
+              make(Opcode::OpGetGlobal, vec![1]),
+              make(Opcode::OpPop, vec![])]),
+        ("let one = 1; let one = 2; one;",
+         vec![Constant::Integer(1), Constant::Integer(2)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpSetGlobal, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpSetGlobal, vec![0]),
+              make(Opcode::OpGetGlobal, vec![0]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_string_expressions() {
+    let tests = vec![
+        ("\"monkey\"",
+         vec![Constant::String("monkey")],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpPop, vec![])]),
+        ("\"mon\" + \"key\"",
+         vec![Constant::String("mon"), Constant::String("key")],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpAdd, vec![]),
+              make(Opcode::OpPop, vec![])]),
+        ("1; \"a\"; 1 + 1; \"a\"",
+         vec![Constant::Integer(1), Constant::String("a")],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpPop, vec![]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpPop, vec![]),
+              make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpAdd, vec![]),
+              make(Opcode::OpPop, vec![]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_array_and_hash_literals() {
+    let tests = vec![
+        ("[]",
+         vec![],
+         vec![make(Opcode::OpArray, vec![0]),
+              make(Opcode::OpPop, vec![])]),
+        ("[1, 2 + 3]",
+         vec![Constant::Integer(1), Constant::Integer(2), Constant::Integer(3)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpConstant, vec![2]),
+              make(Opcode::OpAdd, vec![]),
+              make(Opcode::OpArray, vec![2]),
+              make(Opcode::OpPop, vec![])]),
+        ("{}",
+         vec![],
+         vec![make(Opcode::OpHash, vec![0]),
+              make(Opcode::OpPop, vec![])]),
+        ("{1: 2, 3: 4}",
+         vec![Constant::Integer(1), Constant::Integer(2), Constant::Integer(3), Constant::Integer(4)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpConstant, vec![2]),
+              make(Opcode::OpConstant, vec![3]),
+              make(Opcode::OpHash, vec![4]),
+              make(Opcode::OpPop, vec![])]),
+        ("[1][0]",
+         vec![Constant::Integer(1), Constant::Integer(0)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpArray, vec![1]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpIndex, vec![]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_builtins() {
+    let tests = vec![
+        ("len([]); push([], 1);",
+         vec![Constant::Integer(1)],
+         vec![make(Opcode::OpGetBuiltin, vec![0]),
+              make(Opcode::OpArray, vec![0]),
+              make(Opcode::OpCall, vec![1]),
+              make(Opcode::OpPop, vec![]),
+              make(Opcode::OpGetBuiltin, vec![5]),
+              make(Opcode::OpArray, vec![0]),
+              make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpCall, vec![2]),
+              make(Opcode::OpPop, vec![])]),
+        ("let len = 1; len",
+         vec![Constant::Integer(1)],
+         vec![make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpSetGlobal, vec![0]),
+              make(Opcode::OpGetGlobal, vec![0]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_functions() {
+    let tests = vec![
+        ("fn() { return 5 + 10 }",
+         vec![Constant::Integer(5),
+              Constant::Integer(10),
+              Constant::Instructions(vec![make(Opcode::OpConstant, vec![0]),
+                                          make(Opcode::OpConstant, vec![1]),
+                                          make(Opcode::OpAdd, vec![]),
+                                          make(Opcode::OpReturnValue, vec![])])],
+         vec![make(Opcode::OpClosure, vec![2, 0]),
+              make(Opcode::OpPop, vec![])]),
+        ("fn() { 1; 2 }",
+         vec![Constant::Integer(1),
+              Constant::Integer(2),
+              Constant::Instructions(vec![make(Opcode::OpConstant, vec![0]),
+                                          make(Opcode::OpPop, vec![]),
+                                          make(Opcode::OpConstant, vec![1]),
+                                          make(Opcode::OpReturnValue, vec![])])],
+         vec![make(Opcode::OpClosure, vec![2, 0]),
+              make(Opcode::OpPop, vec![])]),
+        ("fn() { }",
+         vec![Constant::Instructions(vec![make(Opcode::OpReturn, vec![])])],
+         vec![make(Opcode::OpClosure, vec![0, 0]),
+              make(Opcode::OpPop, vec![])]),
+        ("let oneArg = fn(a) { a }; oneArg(24);",
+         vec![Constant::Instructions(vec![make(Opcode::OpGetLocal, vec![0]),
+                                          make(Opcode::OpReturnValue, vec![])]),
+              Constant::Integer(24)],
+         vec![make(Opcode::OpClosure, vec![0, 0]),
+              make(Opcode::OpSetGlobal, vec![0]),
+              make(Opcode::OpGetGlobal, vec![0]),
+              make(Opcode::OpConstant, vec![1]),
+              make(Opcode::OpCall, vec![1]),
+              make(Opcode::OpPop, vec![])]),
+        ("fn() { let num = 55; num }",
+         vec![Constant::Integer(55),
+              Constant::Instructions(vec![make(Opcode::OpConstant, vec![0]),
+                                          make(Opcode::OpSetLocal, vec![0]),
+                                          make(Opcode::OpGetLocal, vec![0]),
+                                          make(Opcode::OpReturnValue, vec![])])],
+         vec![make(Opcode::OpClosure, vec![1, 0]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_closures() {
+    let tests = vec![
+        ("fn(a) { fn(b) { a + b } }",
+         vec![Constant::Instructions(vec![make(Opcode::OpGetFree, vec![0]),
+                                          make(Opcode::OpGetLocal, vec![0]),
+                                          make(Opcode::OpAdd, vec![]),
+                                          make(Opcode::OpReturnValue, vec![])]),
+              Constant::Instructions(vec![make(Opcode::OpCaptureLocal, vec![0]),
+                                          make(Opcode::OpClosure, vec![0, 1]),
+                                          make(Opcode::OpReturnValue, vec![])])],
+         vec![make(Opcode::OpClosure, vec![1, 0]),
+              make(Opcode::OpPop, vec![])]),
+        ("fn(a) { fn(b) { fn(c) { a + b + c } } }",
+         vec![Constant::Instructions(vec![make(Opcode::OpGetFree, vec![0]),
+                                          make(Opcode::OpGetFree, vec![1]),
+                                          make(Opcode::OpAdd, vec![]),
+                                          make(Opcode::OpGetLocal, vec![0]),
+                                          make(Opcode::OpAdd, vec![]),
+                                          make(Opcode::OpReturnValue, vec![])]),
+              Constant::Instructions(vec![make(Opcode::OpCaptureFree, vec![0]),
+                                          make(Opcode::OpCaptureLocal, vec![0]),
+                                          make(Opcode::OpClosure, vec![0, 2]),
+                                          make(Opcode::OpReturnValue, vec![])]),
+              Constant::Instructions(vec![make(Opcode::OpCaptureLocal, vec![0]),
+                                          make(Opcode::OpClosure, vec![1, 1]),
+                                          make(Opcode::OpReturnValue, vec![])])],
+         vec![make(Opcode::OpClosure, vec![2, 0]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_recursive_functions() {
+    let tests = vec![
+        ("let countDown = fn(x) { countDown(x - 1); }; countDown(1);",
+         vec![Constant::Integer(1),
+              Constant::Instructions(vec![make(Opcode::OpCurrentClosure, vec![]),
+                                          make(Opcode::OpGetLocal, vec![0]),
+                                          make(Opcode::OpConstant, vec![0]),
+                                          make(Opcode::OpSub, vec![]),
+                                          make(Opcode::OpCall, vec![1]),
+                                          make(Opcode::OpReturnValue, vec![])])],
+         vec![make(Opcode::OpClosure, vec![1, 0]),
+              make(Opcode::OpSetGlobal, vec![0]),
+              make(Opcode::OpGetGlobal, vec![0]),
+              make(Opcode::OpConstant, vec![0]),
+              make(Opcode::OpCall, vec![1]),
+              make(Opcode::OpPop, vec![])]),
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_compiler_errors() {
+    let tests = [("x", "identifier not found: x"),
+                 ("let a = 1; a + b", "identifier not found: b"),
+                 ("let a = fn() { b }; a()", "identifier not found: b"),
+                 ("b; let b = 1;", "identifier not found: b"),
+    ];
+
+    for test in tests.iter() {
+        let mut compiler = Compiler::new();
+        match compiler.compile(parse(test.0)) {
+            Err(error)  => assert_eq!(error, test.1),
+            Ok(_)       => panic!("expected compiler error for {}", test.0),
+        }
+    }
+}
+
+// Distinct identifiers that never collide with a keyword: va, vb, ..., vba, ...
+fn name(mut i: usize) -> String {
+    let mut name = String::new();
+    loop {
+        name.insert(0, (b'a' + (i % 26) as u8) as char);
+        i /= 26;
+        if i == 0 {
+            return format!("v{}", name);
+        }
+    }
+}
+
+fn lets(names: std::ops::Range<usize>) -> String {
+    names.map(|i| format!("let {} = true;", name(i))).collect()
+}
+
+fn uses(names: std::ops::Range<usize>) -> String {
+    names.map(|i| format!("{};", name(i))).collect()
+}
+
+#[test]
+fn test_compiler_limits() {
+    let tests = [(format!("fn() {{ {} }}", lets(0..255)), None),
+                 (format!("fn() {{ {} }}", lets(0..256)), Some("too many local bindings in function: at most 255 are supported")),
+                 (format!("fn({}) {{ }}", (0..256).map(name).collect::<Vec<String>>().join(",")),
+                  Some("too many local bindings in function: at most 255 are supported")),
+                 (format!("len({})", vec!["true"; 255].join(",")), None),
+                 (format!("len({})", vec!["true"; 256].join(",")), Some("too many arguments in call: at most 255 are supported")),
+                 (format!("fn() {{ {} fn() {{ {} fn() {{ {} }} }} }}", lets(0..200), lets(200..300), uses(0..300)),
+                  Some("too many free variables in closure: at most 255 are supported")),
+                 ("1;".repeat(65537), None),
+                 ((0..65536).map(|i| format!("{};", i)).collect(), None),
+                 ((0..65537).map(|i| format!("{};", i)).collect(), Some("too many constants: at most 65536 are supported")),
+                 (lets(0..65536), None),
+                 (lets(0..65537), Some("too many global bindings: at most 65536 are supported")),
+                 (format!("[{}]", vec!["true"; 65536].join(",")), Some("too many elements in array literal: at most 65535 are supported")),
+                 (format!("{{{}}}", vec!["true: true"; 32768].join(",")), Some("too many pairs in hash literal: at most 32767 are supported")),
+                 (format!("if (true) {{ {} }}", "true;".repeat(40000)), Some("jump target 80006 does not fit in 2 bytes: too much code in one function")),
+    ];
+
+    for (input, expected) in tests.iter() {
+        let mut compiler = Compiler::new();
+        match (compiler.compile(parse(input)), expected) {
+            (Ok(()), None)                  => {},
+            (Err(error), Some(expected))    => assert_eq!(error, *expected),
+            (Ok(()), Some(expected))        => panic!("expected {:?} for input of length {}", expected, input.len()),
+            (Err(error), None)              => panic!("unexpected error {:?} for input of length {}", error, input.len()),
+        }
+    }
+}
+
+#[test]
+fn test_disassemble() {
+    let mut compiler = Compiler::new();
+    compiler.compile(parse("let f = fn(a) { \"x\" }; f(1)")).unwrap();
+
+    let expected = "constants:
+  0000 String \"x\"
+  0001 CompiledFunction [1 params, 1 locals]
+        0000 OpConstant 0
+        0003 OpReturnValue
+  0002 Integer 1
+main:
+  0000 OpClosure 1 0
+  0004 OpSetGlobal 0
+  0007 OpGetGlobal 0
+  0010 OpConstant 2
+  0013 OpCall 1
+  0015 OpPop
+";
+
+    assert_eq!(compiler.bytecode().disassemble(), expected);
+}
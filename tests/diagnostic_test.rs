@@ -0,0 +1,32 @@
+extern crate simia_vm;
+
+use simia_vm::diagnostic::{ render };
+use simia_vm::token::{ Span };
+
+fn span(line: usize, column: usize, length: usize) -> Span {
+    Span { offset: 0, line, column, length }
+}
+
+#[test]
+fn test_render() {
+    let source = "let x = 1;\n\tadd(x,\n    foo)";
+
+    let tests = [(span(1, 5, 1), "msg\n --> a.monkey:1:5\n  |\n1 | let x = 1;\n  |     ^"),
+                 (span(2, 2, 3), "msg\n --> a.monkey:2:2\n  |\n2 | \tadd(x,\n  | \t^^^"),
+                 (span(3, 9, 0), "msg\n --> a.monkey:3:9\n  |\n3 |     foo)\n  |         ^"),
+                 (span(3, 5, 9), "msg\n --> a.monkey:3:5\n  |\n3 |     foo)\n  |     ^^^^"),
+                 (span(4, 1, 0), "msg\n --> a.monkey:4:1\n  |\n4 | \n  | ^"),
+    ];
+
+    for test in tests.iter() {
+        assert_eq!(render("msg", test.0, source, "a.monkey"), test.1);
+    }
+}
+
+#[test]
+fn test_render_wide_line_numbers() {
+    let source = "\n".repeat(11) + "x";
+
+    assert_eq!(render("msg", span(12, 1, 1), &source, "<stdin>"),
+               "msg\n  --> <stdin>:12:1\n   |\n12 | x\n   | ^");
+}
@@ -0,0 +1,48 @@
+extern crate simia_vm;
+
+mod common;
+
+use std::fs;
+
+use common::{ assert_engines_agree, cases, run_eval, run_vm, Outcome };
+
+#[test]
+fn test_eval_test_cases() {
+    for (input, _, _) in cases::ALL.iter().flat_map(|cases| cases.iter()) {
+        assert_engines_agree(input);
+    }
+}
+
+#[test]
+fn test_corpus() {
+    let mut paths: Vec<_> = fs::read_dir("tests/corpus").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "monkey"))
+        .collect();
+    paths.sort();
+
+    assert!(!paths.is_empty(), "no programs found in tests/corpus");
+
+    for path in paths.iter() {
+        let input = fs::read_to_string(path).unwrap();
+        assert_engines_agree(&input);
+    }
+}
+
+#[test]
+fn test_errors_compare_full_messages() {
+    assert_eq!(run_eval("1 < true"), Outcome::Error { message: "type mismatch: Integer < Boolean".to_string() });
+    assert_ne!(run_eval("1 < true"), run_eval("true < 1"));
+    assert_engines_agree("1 < true");
+}
+
+// A function may refer to a global that a later top-level `let` binds, but
+// only `eval` notices when it runs before that `let` has; the VM reads the
+// global's reserved slot and gets null.
+#[test]
+fn test_global_read_before_its_let() {
+    let input = "let a = fn() { b }; a(); let b = 1;";
+
+    assert_eq!(run_eval(input), Outcome::Error { message: "identifier not found: b".to_string() });
+    assert_eq!(run_vm(input), Outcome::Value { kind: "Null".to_string(), inspect: "null".to_string() });
+}
@@ -0,0 +1,37 @@
+extern crate simia_vm;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use simia_vm::environment::{ Environment };
+use simia_vm::object::{ Object };
+
+#[test]
+fn test_environment() {
+    let outer = Rc::new(RefCell::new(Environment::new()));
+    outer.borrow_mut().set("a".to_string(), Object::Integer { value: 1 });
+    outer.borrow_mut().set("b".to_string(), Object::Integer { value: 2 });
+
+    let mut inner = Environment::new_enclosed(Rc::clone(&outer));
+    inner.set("b".to_string(), Object::Integer { value: 3 });
+
+    let tests = [("a", Some(1)),
+                 ("b", Some(3)),
+                 ("c", None),
+    ];
+
+    for test in tests.iter() {
+        match (inner.get(test.0), test.1) {
+            (Some(Object::Integer { value }), Some(expected)) => assert_eq!(value, expected),
+            (None, None)                                      => (),
+            (Some(object), _) => panic!("wrong binding for {}. got={}", test.0, object.inspect()),
+            (None, _)         => panic!("{} is not bound", test.0),
+        }
+    }
+
+    let b = outer.borrow().get("b");
+    match b {
+        Some(Object::Integer { value }) => assert_eq!(value, 2),
+        _                               => panic!("outer binding for b was overwritten"),
+    }
+}
@@ -1,16 +1,25 @@
-extern crate simiaVM;
+extern crate simia_vm;
 
-use simiaVM::lexer::{ Lexer };
-use simiaVM::object::{ Object };
-use simiaVM::parser::{ Parser };
-use simiaVM::eval::{ eval };
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use simia_vm::environment::{ Environment };
+use simia_vm::lexer::{ Lexer };
+use simia_vm::object::{ HashKey, Object };
+use simia_vm::parser::{ Parser };
+use simia_vm::eval::{ eval };
+
+use common::cases::{ self, Case };
 
 fn test_eval(input: String) -> Object {
     let l = Lexer::new(input);
     let mut p = Parser::new(l);
     let program = p.parse_program();
+    let env = Rc::new(RefCell::new(Environment::new()));
 
-    eval(program.unwrap())
+    eval(program.unwrap(), &env)
 }
 
 fn test_integer_object(o: Object, expected: i64) {
@@ -20,83 +29,168 @@ fn test_integer_object(o: Object, expected: i64) {
     }
 }
 
-fn test_boolean_object(o: Object, expected: bool) {
-    match o {
-        Object::Boolean { value }   => assert_eq!(value, expected),
-        _                           => panic!("object is not Boolean. got={}",o.kind()),
-    }
+fn test_cases(cases: &'static [Case]) {
+    common::on_large_stack(move || {
+        for (input, kind, inspect) in cases.iter() {
+            let evaluated = test_eval(input.to_string());
+            assert_eq!(evaluated.kind(), *kind, "wrong kind for {}", input);
+            assert_eq!(evaluated.inspect(), *inspect, "wrong value for {}", input);
+        }
+    })
 }
 
 #[test]
 fn test_eval_integer_expression() {
-    let tests = [  
-                    ("5", 5 ),
-                    ("10", 10 ),
-                    ("-5", -5),
-                    ("-10", -10),
-                    ("5 + 5 + 5 + 5 - 10", 10),
-                    ("2 * 2 * 2 * 2 * 2", 32),
-                    ("-50 + 100 +  -50", 0),
-                    ("5 * 2 + 10", 20),
-                    ("5 + 2 * 10", 25),
-                    ("20 + 2 * -10", 0),
-                    ("50 / 2 * 2 + 10", 60),
-                    ("2 * (5 + 10)", 30),
-                    ("3 * 3 * 3 + 10", 37),
-                    ("3 * (3 * 3) + 10", 37),
-                    ("(5 + 10 * 2 + 15 / 3) * 2 + -10", 50),
-    ];
-
-    for test in &tests {
-        let evaluated = test_eval(test.0.to_string());
-        test_integer_object(evaluated, test.1);
-    }
-                
+    test_cases(cases::INTEGER_EXPRESSIONS);
 }
 
 #[test]
 fn test_eval_boolean_expression() {
-    let tests = [
-                    ("true", true),
-                    ("false", false),
-                    ("1 < 2", true),
-                    ("1 > 2", false),
-                    ("1 < 1", false),
-                    ("1 > 1", false),
-                    ("1 == 1", true),
-                    ("1 != 1", false),
-                    ("1 == 2", false),
-                    ("1 != 2", true),
-                    ("true == true", true),
-                    ("false == false", true),
-                    ("true == false", false),
-                    ("true != false", true),
-                    ("false != true", true),
-                    ("(1 < 2) == true", true),
-                    ("(1 < 2) == false", false),
-                    ("(1 > 2) == true", false),
-                    ("(1 > 2) == false", true),
-    ];
+    test_cases(cases::BOOLEAN_EXPRESSIONS);
+}
+
+#[test]
+fn test_bang_operator() {
+    test_cases(cases::BANG_OPERATOR);
+}
+
+#[test]
+fn test_let_statements() {
+    test_cases(cases::LET_STATEMENTS);
+}
+
+#[test]
+fn test_identifier_not_found() {
+    test_cases(cases::IDENTIFIER_NOT_FOUND);
+}
+
+#[test]
+fn test_function_application() {
+    test_cases(cases::FUNCTION_APPLICATION);
+}
+
+#[test]
+fn test_closures() {
+    test_cases(cases::CLOSURES);
+}
+
+#[test]
+fn test_if_else_expressions() {
+    test_cases(cases::IF_ELSE_EXPRESSIONS);
+}
 
-    for test in &tests {
-        let evaluated = test_eval(test.0.to_string());
-        test_boolean_object(evaluated, test.1);
+#[test]
+fn test_return_statements() {
+    test_cases(cases::RETURN_STATEMENTS);
+}
+
+#[test]
+fn test_error_handling() {
+    test_cases(cases::ERRORS);
+}
+
+#[test]
+fn test_strings() {
+    test_cases(cases::STRINGS);
+}
+
+#[test]
+fn test_arrays() {
+    test_cases(cases::ARRAYS);
+}
+
+#[test]
+fn test_hash_expressions() {
+    test_cases(cases::HASHES);
+}
+
+#[test]
+fn test_builtin_functions() {
+    test_cases(cases::BUILTIN_FUNCTIONS);
+}
+
+#[test]
+fn test_hash_literals() {
+    let input = "let two = \"two\";
+    {
+        \"one\": 10 - 9,
+        two: 1 + 1,
+        \"thr\" + \"ee\": 6 / 2,
+        4: 4,
+        true: 5,
+        false: 6
+    }";
+    let evaluated = test_eval(input.to_string());
+
+    match evaluated {
+        Object::Hash { pairs } => {
+            let expected = [(HashKey::String { value: "one".to_string() }, 1),
+                            (HashKey::String { value: "two".to_string() }, 2),
+                            (HashKey::String { value: "three".to_string() }, 3),
+                            (HashKey::Integer { value: 4 }, 4),
+                            (HashKey::Boolean { value: true }, 5),
+                            (HashKey::Boolean { value: false }, 6),
+            ];
+
+            assert_eq!(pairs.len(), expected.len());
+            for (key, value) in expected.iter() {
+                match pairs.get(key) {
+                    Some(object) => test_integer_object(object.clone(), *value),
+                    None         => panic!("no pair for given key {} in pairs", key.inspect()),
+                }
+            }
+        },
+        _   => panic!("object is not Hash. got={}", evaluated.kind()),
     }
+
+    assert_eq!(test_eval("{\"b\": 2, \"a\": [1]}".to_string()).inspect(), "{a: [1], b: 2}");
 }
 
 #[test]
-fn test_bang_operator() {
-    let tests = [
-                    ("!true", false),
-                    ("!false", true),
-                    ("!5", false),
-                    ("!!true", true),
-                    ("!!false", false),
-                    ("!!5", true),
+fn test_builtin_map_reduce() {
+    let input = "
+    let map = fn(arr, f) {
+        let iter = fn(arr, accumulated) {
+            if (len(arr) == 0) {
+                accumulated
+            } else {
+                iter(rest(arr), push(accumulated, f(first(arr))));
+            }
+        };
+        iter(arr, []);
+    };
+    let reduce = fn(arr, initial, f) {
+        let iter = fn(arr, result) {
+            if (len(arr) == 0) {
+                result
+            } else {
+                iter(rest(arr), f(result, first(arr)));
+            }
+        };
+        iter(arr, initial);
+    };
+    let doubled = map([1, 2, 3, 4], fn(x) { x * 2 });
+    reduce(doubled, 0, fn(acc, x) { acc + x });
+    ";
+
+    test_integer_object(test_eval(input.to_string()), 20);
+}
+
+#[test]
+fn test_error_locations() {
+    let tests = [("1 + true", (1, 3)),
+                 ("let x = 5;\nx + y", (2, 5)),
+                 ("let f = fn(x) {\n    x / 0\n};\nf(1)", (2, 7)),
+                 ("len(1)", (1, 4)),
+                 ("[1, 2][5 + true]", (1, 10)),
     ];
 
-    for test in &tests {
-        let evaluated = test_eval(test.0.to_string());
-        test_boolean_object(evaluated, test.1);
+    for (input, (line, column)) in tests.iter() {
+        match test_eval(input.to_string()) {
+            Object::Error { span: Some(span), .. }  => {
+                assert_eq!((span.line, span.column), (*line, *column), "wrong location for {:?}", input);
+            },
+            object  => panic!("expected a located error for {:?}. got={}", input, object.inspect()),
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,7 @@
-extern crate simiaVM;
+extern crate simia_vm;
 
-use simiaVM::token::{ Token, TokenKind };
-use simiaVM::lexer::{ Lexer };
+use simia_vm::token::{ Span, TokenKind };
+use simia_vm::lexer::{ Lexer };
 
 #[test]
 fn test_next_token() {
@@ -19,91 +19,148 @@ fn(a) {               \
     return a + b;     \
 }                     \
 [1, 2, a];            \
+{\"foo\": \"bar\"}        \
 ".to_string();
     let mut lexer = Lexer::new(input);
 
-    let tests = [ Token { kind: TokenKind::Assign, literal: "=".to_string() },
-                  Token { kind: TokenKind::Plus, literal: "+".to_string() },
-                  Token { kind: TokenKind::Minus, literal: "-".to_string() },
-                  Token { kind: TokenKind::Asterisk, literal: "*".to_string() },
-                  Token { kind: TokenKind::Slash, literal: "/".to_string() },
-                  Token { kind: TokenKind::Bang, literal: "!".to_string() },
-                  Token { kind: TokenKind::Lt, literal: "<".to_string() },
-                  Token { kind: TokenKind::Gt, literal: ">".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
-                  Token { kind: TokenKind::Comma, literal: ",".to_string() },
-                  Token { kind: TokenKind::Lparen, literal: "(".to_string() },
-                  Token { kind: TokenKind::Rparen, literal: ")".to_string() },
-                  Token { kind: TokenKind::Lbrace, literal: "{".to_string() },
-                  Token { kind: TokenKind::Rbrace, literal: "}".to_string() },
-                  Token { kind: TokenKind::Lbracket, literal: "[".to_string() },
-                  Token { kind: TokenKind::Rbracket, literal: "]".to_string() },
-                  Token { kind: TokenKind::Eq, literal: "==".to_string() },
-                  Token { kind: TokenKind::NotEq, literal: "!=".to_string() },
-
-                  Token { kind: TokenKind::Integer, literal: "100".to_string() },
-                  Token { kind: TokenKind::Integer, literal: "200".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
-
-                  Token { kind: TokenKind::Identifier, literal: "foo".to_string() },
-                  Token { kind: TokenKind::Identifier, literal: "bar".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
+    let tests = [ (TokenKind::Assign, "=".to_string()),
+                  (TokenKind::Plus, "+".to_string()),
+                  (TokenKind::Minus, "-".to_string()),
+                  (TokenKind::Asterisk, "*".to_string()),
+                  (TokenKind::Slash, "/".to_string()),
+                  (TokenKind::Bang, "!".to_string()),
+                  (TokenKind::Lt, "<".to_string()),
+                  (TokenKind::Gt, ">".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
+                  (TokenKind::Comma, ",".to_string()),
+                  (TokenKind::Lparen, "(".to_string()),
+                  (TokenKind::Rparen, ")".to_string()),
+                  (TokenKind::Lbrace, "{".to_string()),
+                  (TokenKind::Rbrace, "}".to_string()),
+                  (TokenKind::Lbracket, "[".to_string()),
+                  (TokenKind::Rbracket, "]".to_string()),
+                  (TokenKind::Eq, "==".to_string()),
+                  (TokenKind::NotEq, "!=".to_string()),
+
+                  (TokenKind::Integer, "100".to_string()),
+                  (TokenKind::Integer, "200".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
+
+                  (TokenKind::Identifier, "foo".to_string()),
+                  (TokenKind::Identifier, "bar".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
                   
-                  Token { kind: TokenKind::String, literal: "foo".to_string() },
-                  Token { kind: TokenKind::String, literal: "bar".to_string() },
-                  Token { kind: TokenKind::String, literal: "".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
-
-                  Token { kind: TokenKind::True, literal: "true".to_string() },
-                  Token { kind: TokenKind::False, literal: "false".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
-
-                  Token { kind: TokenKind::Return, literal: "return".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
-
-                  Token { kind: TokenKind::If, literal: "if".to_string() },
-                  Token { kind: TokenKind::Lparen, literal: "(".to_string() },
-                  Token { kind: TokenKind::True, literal: "true".to_string() },
-                  Token { kind: TokenKind::Rparen, literal: ")".to_string() },
-                  Token { kind: TokenKind::Lbrace, literal: "{".to_string() },
+                  (TokenKind::String, "foo".to_string()),
+                  (TokenKind::String, "bar".to_string()),
+                  (TokenKind::String, "".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
+
+                  (TokenKind::True, "true".to_string()),
+                  (TokenKind::False, "false".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
+
+                  (TokenKind::Return, "return".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
+
+                  (TokenKind::If, "if".to_string()),
+                  (TokenKind::Lparen, "(".to_string()),
+                  (TokenKind::True, "true".to_string()),
+                  (TokenKind::Rparen, ")".to_string()),
+                  (TokenKind::Lbrace, "{".to_string()),
                   
-                  Token { kind: TokenKind::Return, literal: "return".to_string() },
-                  Token { kind: TokenKind::Identifier, literal: "a".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
+                  (TokenKind::Return, "return".to_string()),
+                  (TokenKind::Identifier, "a".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
 
-                  Token { kind: TokenKind::Rbrace, literal: "}".to_string() },
+                  (TokenKind::Rbrace, "}".to_string()),
                   
 
-                  Token { kind: TokenKind::Function, literal: "fn".to_string() },
-                  Token { kind: TokenKind::Lparen, literal: "(".to_string() },
-                  Token { kind: TokenKind::Identifier, literal: "a".to_string() },
-                  Token { kind: TokenKind::Rparen, literal: ")".to_string() },
-                  Token { kind: TokenKind::Lbrace, literal: "{".to_string() },
+                  (TokenKind::Function, "fn".to_string()),
+                  (TokenKind::Lparen, "(".to_string()),
+                  (TokenKind::Identifier, "a".to_string()),
+                  (TokenKind::Rparen, ")".to_string()),
+                  (TokenKind::Lbrace, "{".to_string()),
                   
-                  Token { kind: TokenKind::Return, literal: "return".to_string() },
-                  Token { kind: TokenKind::Identifier, literal: "a".to_string() },
-                  Token { kind: TokenKind::Plus, literal: "+".to_string() },
-                  Token { kind: TokenKind::Identifier, literal: "b".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
-
-                  Token { kind: TokenKind::Rbrace, literal: "}".to_string() },
-
-                  Token { kind: TokenKind::Lbracket, literal: "[".to_string() },
-                  Token { kind: TokenKind::Integer, literal: "1".to_string() },
-                  Token { kind: TokenKind::Comma, literal: ",".to_string() },
-                  Token { kind: TokenKind::Integer, literal: "2".to_string() },
-                  Token { kind: TokenKind::Comma, literal: ",".to_string() },
-                  Token { kind: TokenKind::Identifier, literal: "a".to_string() },
-                  Token { kind: TokenKind::Rbracket, literal: "]".to_string() },
-                  Token { kind: TokenKind::Semicolon, literal: ";".to_string() },
+                  (TokenKind::Return, "return".to_string()),
+                  (TokenKind::Identifier, "a".to_string()),
+                  (TokenKind::Plus, "+".to_string()),
+                  (TokenKind::Identifier, "b".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
+
+                  (TokenKind::Rbrace, "}".to_string()),
+
+                  (TokenKind::Lbracket, "[".to_string()),
+                  (TokenKind::Integer, "1".to_string()),
+                  (TokenKind::Comma, ",".to_string()),
+                  (TokenKind::Integer, "2".to_string()),
+                  (TokenKind::Comma, ",".to_string()),
+                  (TokenKind::Identifier, "a".to_string()),
+                  (TokenKind::Rbracket, "]".to_string()),
+                  (TokenKind::Semicolon, ";".to_string()),
+
+                  (TokenKind::Lbrace, "{".to_string()),
+                  (TokenKind::String, "foo".to_string()),
+                  (TokenKind::Colon, ":".to_string()),
+                  (TokenKind::String, "bar".to_string()),
+                  (TokenKind::Rbrace, "}".to_string()),
                   
-                  Token { kind: TokenKind::Eof, literal: "\0".to_string() },
+                  (TokenKind::Eof, "\0".to_string()),
     ];
 
     for test in tests.iter() {
         let token = lexer.next_token();
-        assert!(token.kind == test.kind);
-        assert_eq!(token.literal, test.literal);
+        assert!(token.kind == test.0);
+        assert_eq!(token.literal, test.1);
     }
     
 }
+
+#[test]
+fn test_string_escape_sequences() {
+    let input = r#""a\nb" "\t" "\"quoted\"" "back\\slash" "\q" "ünï""#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    let tests = [ (TokenKind::String, "a\nb".to_string()),
+                  (TokenKind::String, "\t".to_string()),
+                  (TokenKind::String, "\"quoted\"".to_string()),
+                  (TokenKind::String, "back\\slash".to_string()),
+                  (TokenKind::String, "\\q".to_string()),
+                  (TokenKind::String, "ünï".to_string()),
+                  (TokenKind::Eof, "\0".to_string()),
+    ];
+
+    for test in tests.iter() {
+        let token = lexer.next_token();
+        assert!(token.kind == test.0);
+        assert_eq!(token.literal, test.1);
+    }
+}
+
+#[test]
+fn test_token_spans() {
+    let input = "let x = 10;\n  \"héllo\" == x\n\tfoo(ab)".to_string();
+    let mut lexer = Lexer::new(input);
+
+    // (kind, offset, line, column, length)
+    let tests = [ (TokenKind::Let, 0, 1, 1, 3),
+                  (TokenKind::Identifier, 4, 1, 5, 1),
+                  (TokenKind::Assign, 6, 1, 7, 1),
+                  (TokenKind::Integer, 8, 1, 9, 2),
+                  (TokenKind::Semicolon, 10, 1, 11, 1),
+                  (TokenKind::String, 14, 2, 3, 7),
+                  (TokenKind::Eq, 23, 2, 11, 2),
+                  (TokenKind::Identifier, 26, 2, 14, 1),
+                  (TokenKind::Identifier, 29, 3, 2, 3),
+                  (TokenKind::Lparen, 32, 3, 5, 1),
+                  (TokenKind::Identifier, 33, 3, 6, 2),
+                  (TokenKind::Rparen, 35, 3, 8, 1),
+                  (TokenKind::Eof, 36, 3, 9, 0),
+    ];
+
+    for test in tests.iter() {
+        let token = lexer.next_token();
+        assert!(token.kind == test.0, "wrong kind. got={:?}", token.kind);
+        assert_eq!(token.span, Span { offset: test.1, line: test.2, column: test.3, length: test.4 },
+                   "wrong span for {:?}", token.kind);
+    }
+}
@@ -1,8 +1,9 @@
-extern crate simiaVM;
+extern crate simia_vm;
 
-use simiaVM::ast::{ Ast };
-use simiaVM::lexer::{ Lexer };
-use simiaVM::parser::{ Parser };
+use simia_vm::ast::{ Ast };
+use simia_vm::lexer::{ Lexer };
+use simia_vm::parser::{ ParseError, Parser };
+use simia_vm::token::{ TokenKind };
 
 #[derive(Clone)]
 enum TestType {
@@ -13,37 +14,37 @@ enum TestType {
 
 fn test_literal(literal: Ast, expected: TestType) -> bool {
     match literal.clone() {
-        Ast::Integer { value } => {
+        Ast::Integer { value, .. } => {
             if let TestType::Integer (expected) = expected {
                 if value == expected {
                     return true;
                 }
                 eprintln!("integer literal not {}, got={}", expected, value);
-                return false;
+                false
             }
             else {
                 panic!("mismatched type");
             }
         },
-        Ast::StringLiteral  { value } => {
+        Ast::StringLiteral  { value, .. } => {
             if let TestType::String (expected) = expected {
                 if *value == expected {
                     return true;
                 }
                 eprintln!("string literal not {}, got={}", expected, value);
-                return false;
+                false
             }
             else {
                 panic!("mismatched type");
             }
         },
-        Ast::Boolean { value } => {
+        Ast::Boolean { value, .. } => {
             if let TestType::Boolean (expected) = expected {
                 if value == expected {
                     return true;
                 }
                 eprintln!("boolean literal not {}, got={}", expected, value);
-                return false;
+                false
             }
             else {
                 panic!("mismatched type");
@@ -66,10 +67,10 @@ fn test_parse_integer_literal_expression() {
 
         parser.check_parser_errors();
         
-        if let Ast::Program { ref mut statements } = program.unwrap() {
+        if let Ast::Program { ref mut statements, .. } = program.unwrap() {
             assert_eq!(statements.len(), 1);
             for statement in statements.iter() {
-                if let Ast::ExpressionStatement { ref expression } = (**statement).clone() {
+                if let Ast::ExpressionStatement { ref expression, .. } = (**statement).clone() {
                     assert!(test_literal((**expression).clone(), test.1.clone()));
                 }
                 else {
@@ -96,11 +97,11 @@ fn test_parse_let_statement() {
 
         parser.check_parser_errors();
 
-        if let Ast::Program { ref mut statements } = program.unwrap() {
+        if let Ast::Program { ref mut statements, .. } = program.unwrap() {
             assert_eq!(statements.len(), 1);
             for statement in statements.iter() {
-                if let Ast::LetStatement { ref identifier, ref value } = **statement {
-                    if let Ast::Identifier { ref value } = **identifier {
+                if let Ast::LetStatement { ref identifier, ref value, .. } = **statement {
+                    if let Ast::Identifier { ref value, .. } = **identifier {
                         assert_eq!(**value, test.1.to_string());
                     }
                     else {
@@ -132,10 +133,10 @@ fn test_parse_return_statement() {
 
         parser.check_parser_errors();
         
-        if let Ast::Program { ref mut statements } = program.unwrap() {
+        if let Ast::Program { ref mut statements, .. } = program.unwrap() {
             assert_eq!(statements.len(), 1);
             for statement in statements.iter() {
-                if let Ast::ReturnStatement { ref return_value } = **statement {
+                if let Ast::ReturnStatement { ref return_value, .. } = **statement {
                     assert!(test_literal((**return_value).clone(), test.1.clone()));
                 }
                 else {
@@ -162,10 +163,10 @@ fn test_parse_boolean_literal() {
 
         parser.check_parser_errors();
         
-        if let Ast::Program { ref mut statements } = program.unwrap() {
+        if let Ast::Program { ref mut statements, .. } = program.unwrap() {
             assert_eq!(statements.len(), 1);
             for statement in statements.iter() {
-                if let Ast::ExpressionStatement { ref expression } = **statement {
+                if let Ast::ExpressionStatement { ref expression, .. } = **statement {
                     assert!(test_literal((**expression).clone(), test.1.clone()));
                 }
                 else {
@@ -193,10 +194,10 @@ fn test_parse_string_literal() {
 
         parser.check_parser_errors();
         
-        if let Ast::Program { ref mut statements } = program.unwrap() {
+        if let Ast::Program { ref mut statements, .. } = program.unwrap() {
             assert_eq!(statements.len(), 1);
             for statement in statements.iter() {
-                if let Ast::ExpressionStatement { ref expression } = **statement {
+                if let Ast::ExpressionStatement { ref expression, .. } = **statement {
                     assert!(test_literal((**expression).clone(), test.1.clone()));
                 }
                 else {
@@ -225,11 +226,11 @@ fn test_parse_prefix_expression() {
 
         parser.check_parser_errors();
         
-        if let Ast::Program { ref mut statements } = program.unwrap() {
+        if let Ast::Program { ref mut statements, .. } = program.unwrap() {
             assert_eq!(statements.len(), 1);
             for statement in statements.iter() {
-                if let Ast::ExpressionStatement { ref expression } = **statement {
-                    if let Ast::PrefixExpression { ref operator, ref right } = **expression {
+                if let Ast::ExpressionStatement { ref expression, .. } = **statement {
+                    if let Ast::PrefixExpression { ref operator, ref right, .. } = **expression {
                         assert_eq!(**operator, test.1.to_string());
                         assert!(test_literal((**right).clone(), test.2.clone()));
                     }
@@ -268,11 +269,11 @@ fn test_parse_infix_expression() {
 
         parser.check_parser_errors();
         
-        if let Ast::Program { ref mut statements } = program.unwrap() {
+        if let Ast::Program { ref mut statements, .. } = program.unwrap() {
             assert_eq!(statements.len(), 1);
             for statement in statements.iter() {
-                if let Ast::ExpressionStatement { ref expression } = **statement {
-                    if let Ast::InfixExpression { ref left, ref operator, ref right } = **expression {
+                if let Ast::ExpressionStatement { ref expression, .. } = **statement {
+                    if let Ast::InfixExpression { ref left, ref operator, ref right, .. } = **expression {
                         assert!(test_literal((**left).clone(), test.1.clone()));
                         assert_eq!(**operator, test.2.to_string());
                         assert!(test_literal((**right).clone(), test.3.clone()));
@@ -294,7 +295,8 @@ fn test_parse_infix_expression() {
 
 #[test]
 fn test_parse_function_expression() {
-    let tests = ["fn(a,b){ return true; }"
+    let tests = ["fn(a,b){ return true; }",
+                 "fn(){ true }"
     ];
 
     for test in tests.iter() {
@@ -341,6 +343,24 @@ fn test_parse_array_literal() {
     }
 }
 
+#[test]
+fn test_parse_hash_literal() {
+    let tests = ["{}",
+                 "{one:1,two:2,three:3}",
+                 "{1:(0 + 1),true:(10 - 8),three:(15 / 5)}"
+    ];
+
+    for test in tests.iter() {
+        let lexer = Lexer::new(test.to_string());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program();
+
+        parser.check_parser_errors();
+
+        assert_eq!(program.unwrap().clone().inspect(), test.to_string());
+    }
+}
+
 #[test]
 fn test_parse_call_expression() {
     let tests = ["add(a,b)"
@@ -360,7 +380,9 @@ fn test_parse_call_expression() {
 #[test]
 fn test_parse_index_expression() {
     let tests = ["[a,b][0]",
-                 "[a,b][(1 + 1)]"
+                 "[a,b][(1 + 1)]",
+                 "((a * [1,2,3,4][(b * c)]) * d)",
+                 "add((a * b[2]),b[1],(2 * [1,2][1]))"
     ];
 
     for test in tests.iter() {
@@ -373,3 +395,173 @@ fn test_parse_index_expression() {
         assert_eq!(program.unwrap().clone().inspect(), test.to_string());
     }
 }
+
+fn collect_spans(node: &Ast, spans: &mut Vec<(String, usize, usize)>) {
+    spans.push((node.kind(), node.span().line, node.span().column));
+
+    let children: Vec<&Ast> = match node {
+        Ast::Program { statements, .. }             |
+        Ast::BlockStatement { statements, .. }      => statements.iter().map(|s| &**s).collect(),
+        Ast::ExpressionStatement { expression, .. } => vec![expression],
+        Ast::LetStatement { identifier, value, .. } => vec![identifier, value],
+        Ast::ReturnStatement { return_value, .. }   => vec![return_value],
+        Ast::PrefixExpression { right, .. }         => vec![right],
+        Ast::InfixExpression { left, right, .. }    => vec![left, right],
+        Ast::IndexExpression { left, index, .. }    => vec![left, index],
+        Ast::CallExpression { function, arguments, .. } => {
+            let mut children = vec![&**function];
+            children.extend(arguments.iter().map(|a| &**a));
+            children
+        },
+        Ast::FunctionLiteral { arguments, body, .. } => {
+            let mut children: Vec<&Ast> = arguments.iter().map(|a| &**a).collect();
+            children.push(body);
+            children
+        },
+        Ast::IfExpression { condition, body, .. }   => vec![condition, body],
+        _                                           => vec![],
+    };
+
+    for child in children {
+        collect_spans(child, spans);
+    }
+}
+
+#[test]
+fn test_node_spans() {
+    let input = "let x = 1 +\n  f(2)[0];\nif (!x) { return \"s\"; }\nfn(a) { a }";
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    parser.check_parser_errors();
+
+    let expected = [("Program", 1, 1),
+                    ("LetStatement", 1, 1),
+                    ("Identifier", 1, 5),
+                    ("InfixExpression", 1, 11),
+                    ("Integer", 1, 9),
+                    ("IndexExpression", 2, 7),
+                    ("CallExpression", 2, 4),
+                    ("Identifier", 2, 3),
+                    ("Integer", 2, 5),
+                    ("Integer", 2, 8),
+                    ("ExpressionStatement", 3, 1),
+                    ("IfExpression", 3, 1),
+                    ("PrefixExpression", 3, 5),
+                    ("Identifier", 3, 6),
+                    ("BlockStatement", 3, 9),
+                    ("ReturnStatement", 3, 11),
+                    ("StringLiteral", 3, 18),
+                    ("ExpressionStatement", 4, 1),
+                    ("FunctionLiteral", 4, 1),
+                    ("Identifier", 4, 4),
+                    ("BlockStatement", 4, 7),
+                    ("ExpressionStatement", 4, 9),
+                    ("Identifier", 4, 9),
+    ];
+
+    let mut spans = Vec::new();
+    collect_spans(&program.unwrap(), &mut spans);
+
+    let expected: Vec<(String, usize, usize)> = expected.iter()
+        .map(|(kind, line, column)| (kind.to_string(), *line, *column))
+        .collect();
+    assert_eq!(spans, expected);
+}
+
+#[test]
+fn test_parse_errors() {
+    let tests = [("let = 1;", "expected next token to be Identifier, got Assign instead", (1, 5, 1)),
+                 ("add(1, 2", "expected next token to be Rparen, got Eof instead", (1, 9, 0)),
+                 ("let x = 1;\n  , 2", "no prefix parse function for Comma found", (2, 3, 1)),
+                 ("99999999999999999999", "could not parse 99999999999999999999 as integer", (1, 1, 20)),
+                 ("1 + @", "illegal character \"@\"", (1, 5, 1)),
+                 ("let x @ 1", "illegal character \"@\"", (1, 7, 1)),
+    ];
+
+    for test in tests.iter() {
+        let lexer = Lexer::new(test.0.to_string());
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+
+        assert!(!parser.errors.is_empty(), "no errors for {}", test.0);
+
+        let error = &parser.errors[0];
+        let span = error.span();
+        assert_eq!(error.to_string(), test.1);
+        assert_eq!((span.line, span.column, span.length), test.2, "wrong span for {}", test.0);
+    }
+
+    let lexer = Lexer::new("let = 1;".to_string());
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+
+    match &parser.errors[0] {
+        ParseError::ExpectedToken { expected, found } => {
+            assert_eq!(*expected, TokenKind::Identifier);
+            assert_eq!(found.kind, TokenKind::Assign);
+        },
+        error   => panic!("wrong error. got={:?}", error),
+    }
+}
+
+#[test]
+fn test_error_recovery() {
+    let tests = [("let a = 1 +;\nlet b = 2;\nlet c = * 3;",
+                  vec![(1, 12, "no prefix parse function for Semicolon found"),
+                       (3, 9, "no prefix parse function for Asterisk found")]),
+                 ("let f = fn(x, 1) { x; y };\nlet g = 1",
+                  vec![(1, 15, "expected next token to be Identifier, got Integer instead")]),
+                 ("let g = fn(x) {\n  let y = * 2;\n  x + y\n};\nlet h = fn() { x +; y };",
+                  vec![(2, 11, "no prefix parse function for Asterisk found"),
+                       (5, 19, "no prefix parse function for Semicolon found")]),
+                 ("puts(g(1)\nlet h = {\"a\" 1, \"b\": 2};\nh",
+                  vec![(2, 1, "expected next token to be Rparen, got Let instead"),
+                       (2, 14, "expected next token to be Colon, got Integer instead")]),
+                 ("if (ok { 1 }\nlet z = 3 @ 4; z",
+                  vec![(1, 8, "expected next token to be Rparen, got Lbrace instead"),
+                       (2, 11, "illegal character \"@\"")]),
+                 ("let f = fn() { puts(1 }; f()",
+                  vec![(1, 23, "expected next token to be Rparen, got Rbrace instead")]),
+                 ("1 }\n2; ) 3",
+                  vec![(1, 3, "no prefix parse function for Rbrace found"),
+                       (2, 4, "no prefix parse function for Rparen found")]),
+                 ("fn() { 1",
+                  vec![(1, 9, "expected next token to be Rbrace, got Eof instead")]),
+                 ("let let x = 1; x",
+                  vec![(1, 5, "expected next token to be Identifier, got Let instead")]),
+                 ("let z = (;\nlet y = 2;",
+                  vec![(1, 10, "no prefix parse function for Semicolon found")]),
+                 ("let z = (;\ny",
+                  vec![(1, 10, "no prefix parse function for Semicolon found")]),
+                 ("a[;];\nb",
+                  vec![(1, 3, "no prefix parse function for Semicolon found")]),
+                 ("[1, ;];\nlet c = 3;",
+                  vec![(1, 5, "no prefix parse function for Semicolon found")]),
+                 ("f(a[;], [;]); g(*)",
+                  vec![(1, 5, "no prefix parse function for Semicolon found"),
+                       (1, 17, "no prefix parse function for Asterisk found")]),
+                 ("fn() { a[; }; 2 +;",
+                  vec![(1, 10, "no prefix parse function for Semicolon found"),
+                       (1, 18, "no prefix parse function for Semicolon found")]),
+                 ("puts(1;\nputs(2 +);",
+                  vec![(1, 7, "expected next token to be Rparen, got Semicolon instead"),
+                       (2, 9, "no prefix parse function for Rparen found")]),
+    ];
+
+    for test in tests.iter() {
+        let lexer = Lexer::new(test.0.to_string());
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+
+        let errors: Vec<(usize, usize, String)> = parser.errors.iter()
+            .map(|error| (error.span().line, error.span().column, error.to_string()))
+            .collect();
+        let expected: Vec<(usize, usize, String)> = test.1.iter()
+            .map(|(line, column, message)| (*line, *column, message.to_string()))
+            .collect();
+
+        assert_eq!(errors, expected, "wrong errors for {}", test.0);
+    }
+}
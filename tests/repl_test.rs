@@ -0,0 +1,175 @@
+extern crate simia_vm;
+
+use simia_vm::cli::{ Engine };
+use simia_vm::repl::*;
+
+#[test]
+fn test_is_incomplete() {
+    let tests = [("let a = 1;", false),
+                 ("", false),
+                 ("fn(x) {", true),
+                 ("fn(x) {\n  x + 1;\n", true),
+                 ("fn(x) {\n  x + 1;\n}", false),
+                 ("add(1,", true),
+                 ("[1, [2, 3]", true),
+                 ("{\"a\": 1", true),
+                 ("1 )", false),
+                 ("let a = ", true),
+                 ("1 +", true),
+                 ("1 +\n2", false),
+                 ("a ==", true),
+                 ("!", true),
+                 ("\"hello", true),
+                 ("\"hello\nworld\"", false),
+                 ("\"escaped \\\"", true),
+                 ("\"\"", false),
+                 ("let s = \"a\" + ", true),
+    ];
+
+    for (input, expected) in tests.iter() {
+        assert_eq!(is_incomplete(input), *expected, "input: {:?}", input);
+    }
+}
+
+#[test]
+fn test_session_keeps_bindings() {
+    let tests = [(vec!["let x = 1;", "x + 1"], "2"),
+                 (vec!["let add = fn(a, b) { a + b };", "add(2, 3)"], "5"),
+                 (vec!["let x = 1;", "let x = x + 10;", "x"], "11"),
+                 (vec!["let a = [1, 2];", "let h = {\"k\": a};", "h[\"k\"][1]"], "2"),
+                 (vec!["let f = fn(n) { if (n == 0) { 0 } else { n + f(n - 1) } };", "f(4)"], "10"),
+                 (vec!["let y = 2;", "let g = fn() { y };", "let y = 3;", "g()"], "3"),
+                 (vec!["let s = \"ab\";", "len(s + \"c\")"], "3"),
+                 (vec!["let x = 5;", "let z = x +;", "x"], "5"),
+                 (vec!["let x = 5;", "let z = nope;", "x * 2"], "10"),
+                 (vec!["let x = 5;", "x + true", "x"], "5"),
+                 (vec!["1", "let f = fn() { 2 };"], "null"),
+                 (vec!["let x = 5;"], "null"),
+    ];
+
+    for engine in [Engine::Eval, Engine::Vm].iter() {
+        for (lines, expected) in tests.iter() {
+            let mut session = Session::new(*engine);
+            let mut last = None;
+            for line in lines.iter() {
+                last = Some(session.eval(line));
+            }
+
+            match last.unwrap() {
+                Ok(object)  => assert_eq!(object.inspect(), *expected, "{:?} on {:?}", lines, engine),
+                Err(errors) => panic!("{:?} on {:?} failed: {:?}", lines, engine, errors),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_session_errors() {
+    let tests = [("let = 1;", "parse error: "),
+                 ("nope", "runtime error: "),
+                 ("1 + true", "runtime error: "),
+    ];
+
+    for (input, prefix) in tests.iter() {
+        let mut session = Session::new(Engine::Eval);
+        let errors = session.eval(input).err().unwrap();
+        assert!(errors[0].starts_with(prefix), "{:?}: {:?}", input, errors);
+    }
+
+    let mut session = Session::new(Engine::Vm);
+    let errors = session.eval("nope").err().unwrap();
+    assert!(errors[0].starts_with("compile error: "), "{:?}", errors);
+
+    // A `let` whose value fails is not bound, on either engine.
+    for engine in [Engine::Eval, Engine::Vm].iter() {
+        let mut session = Session::new(*engine);
+        assert!(session.eval("let y = 1;").is_ok());
+        assert_eq!(session.eval("let x = 1 / 0;").err().unwrap(),
+                   vec!["runtime error: division by zero: 1 / 0".to_string()]);
+        assert!(session.eval("x").is_err());
+        assert_eq!(session.bindings().iter().map(|(name, _)| name.as_str()).collect::<Vec<&str>>(), vec!["y"]);
+        assert_eq!(session.eval("let z = 2; z + y").unwrap().inspect(), "3");
+    }
+
+    // Repeated literals reuse their constants; new ones fail cleanly once
+    // the pool is full and leave the session usable.
+    let literals = |from: i64, to: i64| (from..to).map(|i| format!("{};", i)).collect::<String>();
+    let mut session = Session::new(Engine::Vm);
+    for _ in 0..3 {
+        assert!(session.eval(&literals(0, 40000)).is_ok());
+    }
+    assert_eq!(session.eval(&literals(40000, 70000)).err().unwrap(),
+               vec!["compile error: too many constants: at most 65536 are supported".to_string()]);
+    assert_eq!(session.eval("39999 + 1").unwrap().inspect(), "40000");
+}
+
+fn output(session: &mut Session, input: &str) -> String {
+    match session.handle(input) {
+        Reply::Output(output)   => output,
+        Reply::Quit             => panic!("{:?} quit the session", input),
+    }
+}
+
+#[test]
+fn test_meta_commands() {
+    let tests = [(":tokens let x = 5;", "1:1 Let \"let\"\n1:5 Identifier \"x\"\n1:7 Assign \"=\"\n1:9 Integer \"5\"\n1:10 Semicolon \";\""),
+                 (":ast -a * 2", "((-a) * 2)\nProgram\n  ExpressionStatement\n    InfixExpression *\n      PrefixExpression -\n        Identifier a\n      Integer 2"),
+                 (":ast if (x) { f(\"s\") }", "if(x){ f(s) }\nProgram\n  ExpressionStatement\n    IfExpression\n      Identifier x\n      BlockStatement\n        ExpressionStatement\n          CallExpression\n            Identifier f\n            StringLiteral \"s\""),
+                 (":bytecode 1 + 2", "constants:\n  0000 Integer 1\n  0001 Integer 2\nmain:\n  0000 OpConstant 0\n  0003 OpConstant 1\n  0006 OpAdd\n  0007 OpPop"),
+                 (":ast let = 1;", "parse error: expected next token to be Identifier, got Assign instead"),
+                 (":env", ""),
+                 (":frobnicate", "unknown command: :frobnicate (type :help for a list)"),
+                 (":load does/not/exist.monkey", "cannot read does/not/exist.monkey: "),
+    ];
+
+    for (input, expected) in tests.iter() {
+        let mut session = Session::new(Engine::Vm);
+        let output = output(&mut session, input);
+        assert!(output.starts_with(expected), "{:?}:\n{}", input, output);
+    }
+}
+
+#[test]
+fn test_env_and_load() {
+    let path = std::env::temp_dir().join(format!("simia_repl_load_test_{}.monkey", std::process::id()));
+    std::fs::write(&path, "let double = fn(x) { x * 2 };\nlet b = double(21);").unwrap();
+
+    for engine in [Engine::Eval, Engine::Vm].iter() {
+        let mut session = Session::new(*engine);
+        output(&mut session, "let a = \"hi\";");
+        output(&mut session, &format!(":load {}", path.display()));
+
+        assert_eq!(output(&mut session, "b"), "42", "{:?}", engine);
+        let env = output(&mut session, ":env");
+        let names: Vec<&str> = env.lines().map(|line| line.split(" = ").next().unwrap()).collect();
+        assert_eq!(names, vec!["a", "b", "double"], "{:?}", engine);
+        assert!(env.contains("a = hi\nb = 42"), "{:?}: {}", engine, env);
+        assert!(matches!(session.handle(":quit"), Reply::Quit));
+        assert!(output(&mut session, ":help").contains(":bytecode <source>"));
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_complete() {
+    let names = vec!["apple".to_string(), "length".to_string(), "le".to_string()];
+    let tests = [("le", 0, vec!["le", "len", "length", "let"]),
+                 ("let x = fi", 8, vec!["first"]),
+                 ("re", 0, vec!["rest", "return"]),
+                 ("puts(ap", 5, vec!["apple"]),
+                 ("f(1) + ", 7, vec!["apple", "else", "false", "first", "fn", "if", "last", "le", "len",
+                                     "length", "let", "push", "puts", "rest", "return", "true"]),
+                 ("zz", 0, vec![]),
+                 (":lo", 0, vec![":load"]),
+                 ("  :", 2, vec![":ast", ":bytecode", ":env", ":help", ":load", ":quit", ":tokens"]),
+                 ("{\"k\":fa", 5, vec!["false"]),
+                 (":ast le", 5, vec!["le", "len", "length", "let"]),
+    ];
+
+    for (line, start, expected) in tests.iter() {
+        let (got_start, candidates) = complete(line, line.len(), &names);
+        assert_eq!(got_start, *start, "{:?}", line);
+        assert_eq!(candidates, *expected, "{:?}", line);
+    }
+}
@@ -0,0 +1,143 @@
+extern crate simia_vm;
+
+use simia_vm::code::{ Instructions, Opcode, make };
+use simia_vm::compiler::{ Bytecode, Compiler };
+use simia_vm::lexer::{ Lexer };
+use simia_vm::object::{ CompiledFunction, Object };
+use simia_vm::parser::{ Parser };
+use simia_vm::serialize::{ MAGIC, VERSION };
+use simia_vm::vm::{ VM };
+
+fn compile(input: &str) -> Bytecode {
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    parser.check_parser_errors();
+
+    let mut compiler = Compiler::new();
+    compiler.compile(program.unwrap()).unwrap();
+    compiler.bytecode()
+}
+
+fn run(bytecode: Bytecode) -> Object {
+    let mut vm = VM::new(bytecode);
+    vm.run().unwrap();
+    vm.result()
+}
+
+const PROGRAM: &str = "
+let greet = fn(name) { \"héllo, \" + name };
+let adder = fn(a) { fn(b) { a + b } };
+[greet(\"monkey\"), adder(-9223372036854775807)(1), {1: true}[1]]
+";
+
+#[test]
+fn test_round_trip() {
+    let bytecode = compile(PROGRAM);
+    let bytes = bytecode.serialize().unwrap();
+    let loaded = Bytecode::deserialize(&bytes).unwrap();
+
+    assert_eq!(loaded.instructions.to_string(), bytecode.instructions.to_string());
+    assert_eq!(loaded.constants.len(), bytecode.constants.len());
+    for (want, got) in bytecode.constants.iter().zip(loaded.constants.iter()) {
+        assert_eq!(got.kind(), want.kind());
+        assert_eq!(got.inspect(), want.inspect());
+    }
+
+    assert_eq!(loaded.serialize().unwrap(), bytes);
+    assert_eq!(run(loaded).inspect(), "[héllo, monkey, -9223372036854775806, true]");
+}
+
+#[test]
+fn test_header() {
+    let bytes = compile("1").serialize().unwrap();
+
+    assert_eq!(&bytes[..4], &MAGIC[..]);
+    assert_eq!(&bytes[4..6], &VERSION.to_be_bytes());
+}
+
+#[test]
+fn test_truncated_files() {
+    let bytes = compile(PROGRAM).serialize().unwrap();
+
+    for length in 0..bytes.len() {
+        if Bytecode::deserialize(&bytes[..length]).is_ok() {
+            panic!("accepted bytecode truncated to {} of {} bytes", length, bytes.len());
+        }
+    }
+
+    let error = Bytecode::deserialize(&bytes[..bytes.len() - 1]).err().unwrap();
+    assert!(error.starts_with("truncated bytecode: expected"), "wrong error: {}", error);
+}
+
+#[test]
+fn test_invalid_files() {
+    let bytes = compile("\"a\"").serialize().unwrap();
+
+    let mut wrong_version = bytes.clone();
+    wrong_version[5] = 99;
+
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] = b'X';
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+
+    let mut unknown_tag = bytes.clone();
+    unknown_tag[10] = 7;
+
+    let tests = [(wrong_version, "unsupported bytecode version 99 (expected 3)"),
+                 (wrong_magic, "not a bytecode file: bad magic header"),
+                 (trailing, "malformed bytecode: 1 unexpected trailing bytes at offset 24"),
+                 (unknown_tag, "malformed bytecode: unknown constant tag 7 at offset 10"),
+                 (b"SM".to_vec(), "not a bytecode file: bad magic header"),
+    ];
+
+    for test in tests.iter() {
+        match Bytecode::deserialize(&test.0) {
+            Err(error)  => assert_eq!(error, test.1),
+            Ok(_)       => panic!("expected error {}", test.1),
+        }
+    }
+}
+
+#[test]
+fn test_invalid_instructions() {
+    let function = |instructions: Vec<Vec<u8>>| Object::CompiledFunction {
+        function: std::rc::Rc::new(CompiledFunction {
+            instructions: Instructions::from(instructions.concat()),
+            num_locals: 0,
+            num_parameters: 0,
+        }),
+    };
+
+    let tests = [(vec![], vec![make(Opcode::OpConstant, vec![5])],
+                  "malformed bytecode: OpConstant refers to constant 5 of 0 at offset 0 in main"),
+                 (vec![], vec![make(Opcode::OpTrue, vec![]), make(Opcode::OpConstant, vec![0])[..2].to_vec()],
+                  "malformed bytecode: truncated OpConstant at offset 1 in main"),
+                 (vec![], vec![vec![255]],
+                  "malformed bytecode: unknown opcode 255 at offset 0 in main"),
+                 (vec![Object::Integer { value: 1 }], vec![make(Opcode::OpClosure, vec![0, 0])],
+                  "malformed bytecode: OpClosure refers to constant 0 of type Integer at offset 0 in main"),
+                 (vec![], vec![make(Opcode::OpClosure, vec![1, 0])],
+                  "malformed bytecode: OpClosure refers to constant 1 of 0 at offset 0 in main"),
+                 (vec![], vec![make(Opcode::OpGetBuiltin, vec![200])],
+                  "malformed bytecode: OpGetBuiltin refers to builtin 200 of 6 at offset 0 in main"),
+                 (vec![function(vec![make(Opcode::OpNull, vec![]), make(Opcode::OpConstant, vec![9])])],
+                  vec![make(Opcode::OpClosure, vec![0, 0])],
+                  "malformed bytecode: OpConstant refers to constant 9 of 1 at offset 1 in constant 0"),
+    ];
+
+    for (constants, instructions, expected) in tests.iter() {
+        let bytecode = Bytecode {
+            instructions: Instructions::from(instructions.concat()),
+            constants: constants.clone(),
+        };
+
+        match Bytecode::deserialize(&bytecode.serialize().unwrap()) {
+            Err(error)  => assert_eq!(error, *expected),
+            Ok(_)       => panic!("expected error {}", expected),
+        }
+    }
+}
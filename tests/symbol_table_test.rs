@@ -0,0 +1,197 @@
+extern crate simia_vm;
+
+use simia_vm::symbol_table::{ Symbol, SymbolScope, SymbolTable };
+
+fn symbol(name: &str, scope: SymbolScope, index: usize) -> Symbol {
+    Symbol {
+        name: name.to_string(),
+        scope,
+        index,
+    }
+}
+
+#[test]
+fn test_define() {
+    let mut global = SymbolTable::new();
+    assert_eq!(global.define("a"), symbol("a", SymbolScope::Global, 0));
+    assert_eq!(global.define("b"), symbol("b", SymbolScope::Global, 1));
+
+    let mut first_local = SymbolTable::new_enclosed(global);
+    assert_eq!(first_local.define("c"), symbol("c", SymbolScope::Local, 0));
+    assert_eq!(first_local.define("d"), symbol("d", SymbolScope::Local, 1));
+
+    let mut second_local = SymbolTable::new_enclosed(first_local);
+    assert_eq!(second_local.define("e"), symbol("e", SymbolScope::Local, 0));
+    assert_eq!(second_local.define("f"), symbol("f", SymbolScope::Local, 1));
+}
+
+#[test]
+fn test_redefine_reuses_index() {
+    let mut global = SymbolTable::new();
+    global.define("a");
+    global.define("b");
+    assert_eq!(global.define("a"), symbol("a", SymbolScope::Global, 0));
+    assert_eq!(global.num_definitions, 2);
+
+    let mut local = SymbolTable::new_enclosed(global);
+    local.define("c");
+    assert_eq!(local.define("a"), symbol("a", SymbolScope::Local, 1));
+    assert_eq!(local.define("c"), symbol("c", SymbolScope::Local, 0));
+}
+
+#[test]
+fn test_define_global_from_nested_scope() {
+    let mut global = SymbolTable::new();
+    global.define("a");
+
+    let mut second_local = SymbolTable::new_enclosed(SymbolTable::new_enclosed(global));
+    assert_eq!(second_local.define_global("b"), symbol("b", SymbolScope::Global, 1));
+    assert_eq!(second_local.resolve("b"), Some(symbol("b", SymbolScope::Global, 1)));
+    assert!(second_local.free_symbols.is_empty());
+}
+
+#[test]
+fn test_resolve_global() {
+    let mut global = SymbolTable::new();
+    global.define("a");
+    global.define("b");
+
+    let expected = [symbol("a", SymbolScope::Global, 0),
+                    symbol("b", SymbolScope::Global, 1),
+    ];
+
+    for sym in expected.iter() {
+        assert_eq!(global.resolve(&sym.name), Some(sym.clone()));
+    }
+}
+
+#[test]
+fn test_resolve_nested_local() {
+    let mut global = SymbolTable::new();
+    global.define("a");
+    global.define("b");
+
+    let mut first_local = SymbolTable::new_enclosed(global);
+    first_local.define("c");
+    first_local.define("d");
+
+    let mut second_local = SymbolTable::new_enclosed(first_local.clone());
+    second_local.define("e");
+    second_local.define("f");
+
+    let tests = [(first_local, vec![symbol("a", SymbolScope::Global, 0),
+                                    symbol("b", SymbolScope::Global, 1),
+                                    symbol("c", SymbolScope::Local, 0),
+                                    symbol("d", SymbolScope::Local, 1)]),
+                 (second_local, vec![symbol("a", SymbolScope::Global, 0),
+                                     symbol("b", SymbolScope::Global, 1),
+                                     symbol("e", SymbolScope::Local, 0),
+                                     symbol("f", SymbolScope::Local, 1)]),
+    ];
+
+    for test in tests.iter() {
+        let mut table = test.0.clone();
+        for sym in test.1.iter() {
+            assert_eq!(table.resolve(&sym.name), Some(sym.clone()));
+        }
+    }
+}
+
+#[test]
+fn test_define_resolve_builtins() {
+    let mut global = SymbolTable::new();
+    let expected = [symbol("a", SymbolScope::Builtin, 0),
+                    symbol("c", SymbolScope::Builtin, 1),
+                    symbol("e", SymbolScope::Builtin, 2),
+                    symbol("f", SymbolScope::Builtin, 3),
+    ];
+
+    for (i, sym) in expected.iter().enumerate() {
+        global.define_builtin(i, &sym.name);
+    }
+
+    let first_local = SymbolTable::new_enclosed(global.clone());
+    let second_local = SymbolTable::new_enclosed(first_local.clone());
+
+    for table in [global, first_local, second_local].iter() {
+        let mut table = table.clone();
+        for sym in expected.iter() {
+            assert_eq!(table.resolve(&sym.name), Some(sym.clone()));
+        }
+    }
+}
+
+#[test]
+fn test_resolve_free() {
+    let mut global = SymbolTable::new();
+    global.define("a");
+    global.define("b");
+
+    let mut first_local = SymbolTable::new_enclosed(global);
+    first_local.define("c");
+    first_local.define("d");
+
+    let mut second_local = SymbolTable::new_enclosed(first_local);
+    second_local.define("e");
+    second_local.define("f");
+
+    let expected = [symbol("a", SymbolScope::Global, 0),
+                    symbol("b", SymbolScope::Global, 1),
+                    symbol("c", SymbolScope::Free, 0),
+                    symbol("d", SymbolScope::Free, 1),
+                    symbol("e", SymbolScope::Local, 0),
+                    symbol("f", SymbolScope::Local, 1),
+    ];
+
+    for sym in expected.iter() {
+        assert_eq!(second_local.resolve(&sym.name), Some(sym.clone()));
+    }
+
+    assert_eq!(second_local.free_symbols,
+               vec![symbol("c", SymbolScope::Local, 0),
+                    symbol("d", SymbolScope::Local, 1)]);
+}
+
+#[test]
+fn test_resolve_unresolvable_free() {
+    let mut global = SymbolTable::new();
+    global.define("a");
+
+    let mut first_local = SymbolTable::new_enclosed(global);
+    first_local.define("c");
+
+    let mut second_local = SymbolTable::new_enclosed(first_local);
+    second_local.define("e");
+    second_local.define("f");
+
+    let expected = [symbol("a", SymbolScope::Global, 0),
+                    symbol("c", SymbolScope::Free, 0),
+                    symbol("e", SymbolScope::Local, 0),
+                    symbol("f", SymbolScope::Local, 1),
+    ];
+
+    for sym in expected.iter() {
+        assert_eq!(second_local.resolve(&sym.name), Some(sym.clone()));
+    }
+
+    for name in ["b", "d"].iter() {
+        assert_eq!(second_local.resolve(name), None);
+    }
+}
+
+#[test]
+fn test_define_and_resolve_function_name() {
+    let mut global = SymbolTable::new();
+    global.define_function_name("a");
+
+    assert_eq!(global.resolve("a"), Some(symbol("a", SymbolScope::Function, 0)));
+}
+
+#[test]
+fn test_shadowing_function_name() {
+    let mut global = SymbolTable::new();
+    global.define_function_name("a");
+    global.define("a");
+
+    assert_eq!(global.resolve("a"), Some(symbol("a", SymbolScope::Global, 0)));
+}
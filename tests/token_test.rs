@@ -1,12 +1,13 @@
-#[warn(non_snake_case)]
-extern crate simiaVM;
+extern crate simia_vm;
 
-use simiaVM::token::{ Token, TokenKind };
+use simia_vm::token::{ Span, Token, TokenKind };
 
 #[test]
 fn test_token() {
-    let token = Token { kind: TokenKind::Identifier, literal: "foo".to_string() };
+    let span = Span { offset: 4, line: 1, column: 5, length: 3 };
+    let token = Token { kind: TokenKind::Identifier, literal: "foo".to_string(), span };
 
     assert_eq!(token.kind, TokenKind::Identifier);
     assert_eq!(token.literal, "foo".to_string());
+    assert_eq!(token.span, span);
 }
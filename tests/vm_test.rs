@@ -0,0 +1,303 @@
+extern crate simia_vm;
+
+use simia_vm::ast::{ Ast };
+use simia_vm::code::{ Instructions, Opcode, make };
+use simia_vm::compiler::{ Bytecode, Compiler };
+use simia_vm::lexer::{ Lexer };
+use simia_vm::object::{ Object };
+use simia_vm::parser::{ Parser };
+use simia_vm::vm::{ VM };
+
+fn parse(input: &str) -> Ast {
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    parser.check_parser_errors();
+
+    program.unwrap()
+}
+
+fn run_vm(input: &str) -> Result<Object, String> {
+    let mut compiler = Compiler::new();
+    compiler.compile(parse(input))?;
+
+    let mut vm = VM::new(compiler.bytecode());
+    vm.run()?;
+
+    Ok(vm.result())
+}
+
+// Expected values are given as (kind, inspect) so that e.g. the integer 1
+// and the string "1" are told apart.
+fn run_vm_tests(tests: &[(&str, &str, &str)]) {
+    for test in tests.iter() {
+        match run_vm(test.0) {
+            Ok(object)  => {
+                assert_eq!(object.kind(), test.1, "wrong kind for {}", test.0);
+                assert_eq!(object.inspect(), test.2, "wrong value for {}", test.0);
+            },
+            Err(error)  => panic!("vm error for {}: {}", test.0, error),
+        }
+    }
+}
+
+#[test]
+fn test_integer_arithmetic() {
+    let tests = [("1", "Integer", "1"),
+                 ("2", "Integer", "2"),
+                 ("1 + 2", "Integer", "3"),
+                 ("1 - 2", "Integer", "-1"),
+                 ("1 * 2", "Integer", "2"),
+                 ("4 / 2", "Integer", "2"),
+                 ("50 / 2 * 2 + 10 - 5", "Integer", "55"),
+                 ("5 * (2 + 10)", "Integer", "60"),
+                 ("-5", "Integer", "-5"),
+                 ("-50 + 100 + -50", "Integer", "0"),
+                 ("(5 + 10 * 2 + 15 / 3) * 2 + -10", "Integer", "50"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_boolean_expressions() {
+    let tests = [("true", "Boolean", "true"),
+                 ("false", "Boolean", "false"),
+                 ("1 < 2", "Boolean", "true"),
+                 ("1 > 2", "Boolean", "false"),
+                 ("1 == 1", "Boolean", "true"),
+                 ("1 != 1", "Boolean", "false"),
+                 ("true == true", "Boolean", "true"),
+                 ("true != false", "Boolean", "true"),
+                 ("(1 < 2) == true", "Boolean", "true"),
+                 ("(1 > 2) == false", "Boolean", "true"),
+                 ("!true", "Boolean", "false"),
+                 ("!5", "Boolean", "false"),
+                 ("!!5", "Boolean", "true"),
+                 ("!(if (false) { 5; })", "Boolean", "true"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_conditionals() {
+    let tests = [("if (true) { 10 }", "Integer", "10"),
+                 ("if (true) { 10 } else { 20 }", "Integer", "10"),
+                 ("if (false) { 10 } else { 20 } ", "Integer", "20"),
+                 ("if (1) { 10 }", "Integer", "10"),
+                 ("if (1 < 2) { 10 } else { 20 }", "Integer", "10"),
+                 ("if (1 > 2) { 10 }", "Null", "null"),
+                 ("if (false) { 10 }", "Null", "null"),
+                 ("if ((if (false) { 10 })) { 10 } else { 20 }", "Integer", "20"),
+                 ("if (true) { let a = 1; }", "Null", "null"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_global_let_statements() {
+    let tests = [("let one = 1; one", "Integer", "1"),
+                 ("let one = 1; let two = 2; one + two", "Integer", "3"),
+                 ("let one = 1; let two = one + one; one + two", "Integer", "3"),
+                 ("let x = 5;", "Null", "null"),
+                 ("5; let x = 6;", "Null", "null"),
+                 ("let f = fn() { 1 }; f(); let g = f;", "Null", "null"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_string_expressions() {
+    let tests = [("\"monkey\"", "String", "monkey"),
+                 ("\"mon\" + \"key\"", "String", "monkey"),
+                 ("\"mon\" + \"key\" + \"banana\"", "String", "monkeybanana"),
+                 ("\"a\" == \"a\"", "Boolean", "true"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_array_and_hash_literals() {
+    let tests = [("[]", "Array", "[]"),
+                 ("[1, 2, 3]", "Array", "[1, 2, 3]"),
+                 ("[1 + 2, 3 * 4, 5 + 6]", "Array", "[3, 12, 11]"),
+                 ("{}", "Hash", "{}"),
+                 ("{1: 2, 2: 3}", "Hash", "{1: 2, 2: 3}"),
+                 ("{1 + 1: 2 * 2, 3 + 3: 4 * 4}", "Hash", "{2: 4, 6: 16}"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_index_expressions() {
+    let tests = [("[1, 2, 3][1]", "Integer", "2"),
+                 ("[1, 2, 3][0 + 2]", "Integer", "3"),
+                 ("[[1, 1, 1]][0][0]", "Integer", "1"),
+                 ("[1, 2, 3][-1]", "Integer", "3"),
+                 ("{1: 1, 2: 2}[1]", "Integer", "1"),
+                 ("{1: 1, 2: 2}[2]", "Integer", "2"),
+                 ("{1: 1}[0]", "Null", "null"),
+                 ("{}[0]", "Null", "null"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_builtin_functions() {
+    let tests = [("len(\"\")", "Integer", "0"),
+                 ("len(\"four\")", "Integer", "4"),
+                 ("len([1, 2, 3])", "Integer", "3"),
+                 ("len([])", "Integer", "0"),
+                 ("puts(\"hello\", \"world!\")", "Null", "null"),
+                 ("first([1, 2, 3])", "Integer", "1"),
+                 ("first([])", "Null", "null"),
+                 ("last([1, 2, 3])", "Integer", "3"),
+                 ("rest([1, 2, 3])", "Array", "[2, 3]"),
+                 ("rest([])", "Null", "null"),
+                 ("push([], 1)", "Array", "[1]"),
+                 ("let a = [1]; push(a, 2); a", "Array", "[1]"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_calling_functions() {
+    let tests = [("let fivePlusTen = fn() { 5 + 10; }; fivePlusTen();", "Integer", "15"),
+                 ("let a = fn() { 1 }; let b = fn() { a() + 1 }; b()", "Integer", "2"),
+                 ("let early = fn() { return 99; 100; }; early();", "Integer", "99"),
+                 ("let noReturn = fn() { }; noReturn();", "Null", "null"),
+                 ("let one = fn() { let one = 1; one }; one();", "Integer", "1"),
+                 ("let sum = fn(a, b) { let c = a + b; c; }; sum(1, 2) + sum(3, 4);", "Integer", "10"),
+                 ("let globalNum = 10; let f = fn(a) { let n = a * 2; globalNum + n }; f(1) + f(2)",
+                  "Integer", "26"),
+                 ("let returnsOne = fn() { 1; }; let r = fn() { returnsOne; }; r()();", "Integer", "1"),
+                 ("fn(){ len([1, 2]) }()", "Integer", "2"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_closures() {
+    let tests = [("let newClosure = fn(a) { fn() { a; }; }; let closure = newClosure(99); closure();",
+                  "Integer", "99"),
+                 ("let newAdder = fn(a, b) { fn(c) { a + b + c }; }; newAdder(1, 2)(8);", "Integer", "11"),
+                 ("let newAdder = fn(a) { fn(b) { fn(c) { a + b + c } } }; newAdder(1)(2)(3);",
+                  "Integer", "6"),
+                 ("let x = 10; let f = fn(y) { x + y }; let x = 20; f(1)", "Integer", "21"),
+                 ("let f = fn() { let a = 1; let a = a + 1; a }; f()", "Integer", "2"),
+                 ("let x = 1; let f = fn(a) { let b = 2; fn() { fn() { a + b + x } } }; f(3)()()",
+                  "Integer", "6"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_recursive_functions() {
+    let tests = [("let countDown = fn(x) { if (x == 0) { return 0; } else { countDown(x - 1); } }; countDown(1);",
+                  "Integer", "0"),
+                 ("let wrapper = fn() { let countDown = fn(x) { if (x == 0) { 0 } else { countDown(x - 1) } }; countDown(5) }; wrapper();",
+                  "Integer", "0"),
+                 ("let fibonacci = fn(x) { if (x < 2) { x } else { fibonacci(x - 1) + fibonacci(x - 2) } }; fibonacci(15);",
+                  "Integer", "610"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_top_level_return() {
+    let tests = [("return 10; 9;", "Integer", "10"),
+                 ("if (true) { return 1; } 2", "Integer", "1"),
+                 ("let x = 1; return x; let y = 2;", "Integer", "1"),
+    ];
+
+    run_vm_tests(&tests);
+}
+
+#[test]
+fn test_runtime_errors() {
+    let tests = [("5 + true", "type mismatch: Integer + Boolean"),
+                 ("-true", "unknown operator: -Boolean"),
+                 ("true + false", "unknown operator: Boolean + Boolean"),
+                 ("10 / 0", "division by zero: 10 / 0"),
+                 ("9223372036854775807 + 1", "integer overflow: 9223372036854775807 + 1"),
+                 ("\"a\" - \"b\"", "unknown operator: String - String"),
+                 ("[1, 2, 3][3]", "index out of bounds: index=3, length=3"),
+                 ("{[1]: 2}", "unusable as hash key: Array"),
+                 ("1[0]", "index operator not supported: Integer[Integer]"),
+                 ("len(1)", "argument to `len` not supported, got Integer"),
+                 ("len(\"one\", \"two\")", "wrong number of arguments: want=1, got=2"),
+                 ("first(1)", "argument to `first` must be Array, got Integer"),
+                 ("push(1, 1)", "argument to `push` must be Array, got Integer"),
+                 ("1(2)", "not a function: Integer"),
+                 ("fn() { 1; }(1);", "wrong number of arguments: want=0, got=1"),
+                 ("fn(a, b) { a + b; }(1);", "wrong number of arguments: want=2, got=1"),
+                 ("let f = fn() { f() }; f()", "stack overflow"),
+    ];
+
+    for test in tests.iter() {
+        match run_vm(test.0) {
+            Err(error)  => assert_eq!(error, test.1),
+            Ok(object)  => panic!("expected vm error for {}. got={}", test.0, object.inspect()),
+        }
+    }
+}
+
+// Bytecode that the compiler never produces, as it might come from a
+// damaged .smb file, must fail with an error rather than a panic.
+#[test]
+fn test_malformed_bytecode() {
+    let function = Object::CompiledFunction {
+        function: std::rc::Rc::new(simia_vm::object::CompiledFunction {
+            instructions: Instructions::from(make(Opcode::OpReturn, vec![])),
+            num_locals: 0,
+            num_parameters: 0,
+        }),
+    };
+
+    let no_return = Object::CompiledFunction {
+        function: std::rc::Rc::new(simia_vm::object::CompiledFunction {
+            instructions: Instructions::from(make(Opcode::OpTrue, vec![])),
+            num_locals: 0,
+            num_parameters: 0,
+        }),
+    };
+
+    let tests = [(vec![], vec![make(Opcode::OpPop, vec![])], "stack underflow"),
+                 (vec![], vec![make(Opcode::OpAdd, vec![])], "stack underflow"),
+                 (vec![], vec![make(Opcode::OpConstant, vec![5])], "constant index 5 out of range"),
+                 (vec![], vec![make(Opcode::OpConstant, vec![0])[..2].to_vec()], "truncated operand at offset 1"),
+                 (vec![], vec![make(Opcode::OpGetBuiltin, vec![200])], "builtin index 200 out of range"),
+                 (vec![], vec![make(Opcode::OpGetFree, vec![0])], "free variable index 0 out of range"),
+                 (vec![], vec![make(Opcode::OpTrue, vec![]), make(Opcode::OpArray, vec![3])], "stack underflow"),
+                 (vec![], vec![make(Opcode::OpTrue, vec![]), make(Opcode::OpHash, vec![1])],
+                  "hash literal with an odd number of elements"),
+                 (vec![], vec![make(Opcode::OpCall, vec![0])], "stack underflow"),
+                 (vec![function.clone()], vec![make(Opcode::OpClosure, vec![0, 2])], "stack underflow"),
+                 (vec![function], vec![make(Opcode::OpClosure, vec![1, 0])], "constant index 1 out of range"),
+                 (vec![no_return], vec![make(Opcode::OpClosure, vec![0, 0]), make(Opcode::OpCall, vec![0])],
+                  "function ended without return"),
+    ];
+
+    for (constants, instructions, expected) in tests.iter() {
+        let mut vm = VM::new(Bytecode {
+            instructions: Instructions::from(instructions.concat()),
+            constants: constants.clone(),
+        });
+
+        match vm.run() {
+            Err(error)  => assert_eq!(error, *expected),
+            Ok(())      => panic!("expected vm error {}", expected),
+        }
+    }
+}